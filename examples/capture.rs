@@ -75,7 +75,7 @@ fn main() -> ! {
     }
 }
 
-fn print_num<U: SerialUsci>(tx: &mut Tx<U>, num: u16) {
+fn print_num<U: SerialUsci, P>(tx: &mut Tx<U, P>, num: u16) {
     write(tx, '0');
     write(tx, 'x');
     print_hex(tx, num >> 12);
@@ -85,7 +85,7 @@ fn print_num<U: SerialUsci>(tx: &mut Tx<U>, num: u16) {
     write(tx, '\n');
 }
 
-fn print_hex<U: SerialUsci>(tx: &mut Tx<U>, h: u16) {
+fn print_hex<U: SerialUsci, P>(tx: &mut Tx<U, P>, h: u16) {
     let c = match h {
         0 => '0',
         1 => '1',
@@ -108,7 +108,7 @@ fn print_hex<U: SerialUsci>(tx: &mut Tx<U>, h: u16) {
     write(tx, c);
 }
 
-fn write<U: SerialUsci>(tx: &mut Tx<U>, ch: char) {
+fn write<U: SerialUsci, P>(tx: &mut Tx<U, P>, ch: char) {
     block!(tx.write(ch as u8)).void_unwrap();
 }
 
@@ -63,6 +63,8 @@ fn main() -> ! {
                         RecvError::Parity => '!',
                         RecvError::Overrun(_) => '}',
                         RecvError::Framing => '?',
+                        RecvError::Break => '#',
+                        _ => '*',
                     }) as u8
                 }
             };
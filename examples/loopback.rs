@@ -24,7 +24,7 @@ fn setup_uart<S: SerialUsci>(
     loopback: Loopback,
     baudrate: u32,
     smclk: &Smclk,
-) -> (Tx<S>, Rx<S>) {
+) -> (Tx<S, S::TxPin>, Rx<S, S::RxPin>) {
     SerialConfig::new(
         usci,
         BitOrder::LsbFirst,
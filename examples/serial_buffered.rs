@@ -0,0 +1,92 @@
+#![no_main]
+#![no_std]
+#![feature(abi_msp430_interrupt)]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+use critical_section::with;
+use msp430::interrupt::{enable as enable_int, Mutex};
+use msp430_rt::entry;
+use msp430fr2355::interrupt;
+use msp430fr2x5x_hal::{
+    clock::{ClockConfig, DcoclkFreqSel, MclkDiv, SmclkDiv},
+    fram::Fram,
+    gpio::{Alternate1, Batch, Floating, Input, Pin, Pin2, Pin3, P4},
+    pmm::Pmm,
+    serial::*,
+    watchdog::Wdt,
+};
+use panic_msp430 as _;
+
+type Serial = BufferedSerial<
+    msp430fr2355::E_USCI_A1,
+    Pin<P4, Pin3, Alternate1<Input<Floating>>>,
+    Pin<P4, Pin2, Alternate1<Input<Floating>>>,
+    64,
+    32,
+>;
+
+static SERIAL: Mutex<RefCell<Option<Serial>>> = Mutex::new(RefCell::new(None));
+
+// Prints a greeting then echos on UART1, with the eUSCI interrupt moving bytes in the background
+#[entry]
+fn main() -> ! {
+    let periph = msp430fr2355::Peripherals::take().unwrap();
+
+    let mut fram = Fram::new(periph.FRCTL);
+    let _wdt = Wdt::constrain(periph.WDT_A);
+
+    let (_smclk, aclk, _delay) = ClockConfig::new(periph.CS)
+        .mclk_dcoclk(DcoclkFreqSel::_1MHz, MclkDiv::_1)
+        .smclk_on(SmclkDiv::_2)
+        .aclk_refoclk()
+        .freeze(&mut fram);
+
+    let pmm = Pmm::new(periph.PMM);
+    let p4 = Batch::new(periph.P4).split(&pmm);
+
+    let (tx, rx) = SerialConfig::new(
+        periph.E_USCI_A1,
+        BitOrder::LsbFirst,
+        BitCount::EightBits,
+        StopBits::OneStopBit,
+        Parity::NoParity,
+        Loopback::NoLoop,
+        9600,
+    )
+    .use_aclk(&aclk)
+    .split(p4.pin3.to_alternate1(), p4.pin2.to_alternate1());
+
+    let mut serial = BufferedSerial::new(tx, rx);
+    writeln!(serial, "HELLO").ok();
+    with(|cs| SERIAL.borrow_ref_mut(cs).replace(serial));
+
+    unsafe { enable_int() };
+
+    let mut buf = [0; 16];
+    loop {
+        with(|cs| {
+            if let Some(ref mut serial) = *SERIAL.borrow_ref_mut(cs) {
+                let n = serial.read(&mut buf);
+                serial.write(&buf[..n]);
+            }
+        });
+    }
+}
+
+#[interrupt]
+fn EUSCI_A1() {
+    with(|cs| {
+        if let Some(ref mut serial) = *SERIAL.borrow_ref_mut(cs) {
+            serial.handle_interrupt();
+        }
+    });
+}
+
+// The compiler will emit calls to the abort() compiler intrinsic if debug assertions are
+// enabled (default for dev profile). MSP430 does not actually have meaningful abort() support
+// so for now, we create our own in each application where debug assertions are present.
+#[no_mangle]
+extern "C" fn abort() -> ! {
+    panic!();
+}
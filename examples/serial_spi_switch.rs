@@ -0,0 +1,91 @@
+#![no_main]
+#![no_std]
+
+use embedded_hal::blocking::spi::Transfer;
+use embedded_hal::digital::v2::OutputPin;
+use embedded_hal::prelude::*;
+use embedded_hal::spi::MODE_0;
+use msp430_rt::entry;
+use msp430fr2x5x_hal::{
+    clock::{ClockConfig, DcoclkFreqSel, MclkDiv, SmclkDiv},
+    fram::Fram,
+    gpio::Batch,
+    pmm::Pmm,
+    serial::{BitCount, BitOrder, Loopback, Parity, SerialConfig, StopBits},
+    spi::SpiBusConfig,
+    watchdog::Wdt,
+};
+use nb::block;
+use panic_msp430 as _;
+
+// Uses E_USCI_A1 as a UART, then switches it to an SPI bus on the same pins to talk to a device
+// with a software chip select on P4.4, then switches back to UART.
+// P4.3 is UART Tx / SPI MISO, P4.2 is UART Rx / SPI MOSI and P4.1 is SPI SCLK.
+#[entry]
+fn main() -> ! {
+    let periph = msp430fr2355::Peripherals::take().unwrap();
+
+    let mut fram = Fram::new(periph.FRCTL);
+    let _wdt = Wdt::constrain(periph.WDT_A);
+
+    let pmm = Pmm::new(periph.PMM);
+    let p4 = Batch::new(periph.P4).split(&pmm);
+    let sck = p4.pin1.to_alternate1();
+    let mut cs = p4.pin4.to_output();
+    cs.set_high().ok();
+
+    let (smclk, _aclk, _delay) = ClockConfig::new(periph.CS)
+        .mclk_dcoclk(DcoclkFreqSel::_8MHz, MclkDiv::_1)
+        .smclk_on(SmclkDiv::_1)
+        .aclk_vloclk()
+        .freeze(&mut fram);
+
+    let uart_config = |usci| {
+        SerialConfig::new(
+            usci,
+            BitOrder::LsbFirst,
+            BitCount::EightBits,
+            StopBits::OneStopBit,
+            Parity::NoParity,
+            Loopback::NoLoop,
+            115200,
+        )
+        .use_smclk(&smclk)
+    };
+
+    let (mut tx, rx) = uart_config(periph.E_USCI_A1)
+        .split(p4.pin3.to_alternate1(), p4.pin2.to_alternate1());
+    tx.bwrite_all(b"UART mode\r\n").ok();
+    tx.bflush().ok();
+
+    // The Tx and Rx pins come back unchanged and can be used as MISO and MOSI
+    let (usci, tx_pin, rx_pin) = tx.release(rx);
+    let mut spi = SpiBusConfig::new(usci, MODE_0, BitOrder::MsbFirst, BitCount::EightBits)
+        .use_smclk(&smclk, 8) // 8MHz / 8 = 1MHz
+        .configure_with_software_cs(tx_pin, rx_pin, sck);
+
+    cs.set_low().ok();
+    let mut buf = [0x9F, 0, 0, 0];
+    spi.transfer(&mut buf).ok();
+    cs.set_high().ok();
+
+    let (usci, (miso, mosi, _sck)) = spi.release();
+    let (mut tx, mut rx) = uart_config(usci).split(miso, mosi);
+    tx.bwrite_all(b"UART mode again\r\n").ok();
+
+    // Send the JEDEC ID read over SPI, then echo anything received
+    tx.bwrite_all(&buf[1..]).ok();
+    loop {
+        if let Ok(byte) = block!(rx.read()) {
+            block!(tx.write(byte)).ok();
+        }
+    }
+}
+
+// The compiler will emit calls to the abort() compiler intrinsic if debug assertions are
+// enabled (default for dev profile). MSP430 does not actually have meaningful abort() support
+// so for now, we create our own in each application where debug assertions are present.
+#[no_mangle]
+extern "C" fn abort() -> ! {
+    panic!();
+}
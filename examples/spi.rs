@@ -7,7 +7,7 @@ use embedded_hal::spi::MODE_0;
 use embedded_hal::blocking::delay::DelayMs;
 use msp430_rt::entry;
 use msp430fr2x5x_hal::{
-    clock::{ClockConfig, DcoclkFreqSel, MclkDiv, SmclkDiv}, fram::Fram, gpio::Batch, pmm::Pmm, spi::SpiBusConfig, watchdog::Wdt
+    clock::{ClockConfig, DcoclkFreqSel, MclkDiv, SmclkDiv}, fram::Fram, gpio::Batch, pmm::Pmm, spi::{BitCount, BitOrder, SpiBusConfig}, watchdog::Wdt
 };
 use nb::block;
 use panic_msp430 as _;
@@ -33,7 +33,7 @@ fn main() -> ! {
         .aclk_vloclk()
         .freeze(&mut fram);
 
-    let mut spi = SpiBusConfig::new(periph.E_USCI_A0, MODE_0, true)
+    let mut spi = SpiBusConfig::new(periph.E_USCI_A0, MODE_0, BitOrder::MsbFirst, BitCount::EightBits)
         .use_smclk(&smclk, 16) // 8MHz / 16 = 500kHz
         .configure_with_hardware_cs(miso, mosi, sck, cs);
 
@@ -0,0 +1,111 @@
+#![no_main]
+#![no_std]
+#![feature(abi_msp430_interrupt)]
+
+use core::cell::RefCell;
+use critical_section::with;
+use embedded_hal::spi::MODE_0;
+use msp430::interrupt::{enable as enable_int, Mutex};
+use msp430_rt::entry;
+use msp430fr2355::interrupt;
+use msp430fr2x5x_hal::{
+    clock::{ClockConfig, DcoclkFreqSel, MclkDiv, SmclkDiv},
+    fram::Fram,
+    gpio::{Alternate1, Batch, Floating, Input, Pin, Pin4, Pin5, Pin6, Pin7, P1},
+    pmm::Pmm,
+    spi::{BitCount, BitOrder, SpiBusConfig, SpiTransfer},
+    watchdog::Wdt,
+};
+use panic_msp430 as _;
+
+type SpiPins = (
+    Pin<P1, Pin7, Alternate1<Input<Floating>>>,
+    Pin<P1, Pin6, Alternate1<Input<Floating>>>,
+    Pin<P1, Pin5, Alternate1<Input<Floating>>>,
+    Pin<P1, Pin4, Alternate1<Input<Floating>>>,
+);
+
+static TRANSFER: Mutex<RefCell<Option<SpiTransfer<'static, msp430fr2355::E_USCI_A0, SpiPins>>>> =
+    Mutex::new(RefCell::new(None));
+
+static mut TX_BUF: [u8; 32] = [0b10101010; 32];
+static mut RX_BUF: [u8; 32] = [0; 32];
+
+// Sends a buffer over SPI in the background while the CPU is free to do other work
+#[entry]
+fn main() -> ! {
+    let periph = msp430fr2355::Peripherals::take().unwrap();
+
+    let mut fram = Fram::new(periph.FRCTL);
+    let _wdt = Wdt::constrain(periph.WDT_A);
+
+    let pmm = Pmm::new(periph.PMM);
+    let p1 = Batch::new(periph.P1).split(&pmm);
+    let miso = p1.pin7.to_alternate1();
+    let mosi = p1.pin6.to_alternate1();
+    let sck = p1.pin5.to_alternate1();
+    let cs = p1.pin4.to_alternate1();
+
+    let (smclk, _aclk, _delay) = ClockConfig::new(periph.CS)
+        .mclk_dcoclk(DcoclkFreqSel::_8MHz, MclkDiv::_1)
+        .smclk_on(SmclkDiv::_1)
+        .aclk_vloclk()
+        .freeze(&mut fram);
+
+    let spi = SpiBusConfig::new(
+        periph.E_USCI_A0,
+        MODE_0,
+        BitOrder::MsbFirst,
+        BitCount::EightBits,
+    )
+        .use_smclk_freq(&smclk, 500_000)
+        .configure_with_hardware_cs(miso, mosi, sck, cs);
+
+    // Safety: the buffers are only accessed through the transfer from here on
+    let (rx_buf, tx_buf) = unsafe {
+        (
+            &mut *core::ptr::addr_of_mut!(RX_BUF),
+            &*core::ptr::addr_of!(TX_BUF),
+        )
+    };
+    let transfer = spi.start_transfer(rx_buf, tx_buf);
+    with(|cs| TRANSFER.borrow_ref_mut(cs).replace(transfer));
+
+    unsafe { enable_int() };
+
+    loop {
+        let done = with(|cs| {
+            TRANSFER
+                .borrow_ref(cs)
+                .as_ref()
+                .is_some_and(|t| t.is_done())
+        });
+        if done {
+            // The received bytes are now in RX_BUF
+            let _spi = with(|cs| TRANSFER.borrow_ref_mut(cs).take()).unwrap().free();
+            break;
+        }
+        // Do other work here
+    }
+
+    loop {
+        msp430::asm::nop();
+    }
+}
+
+#[interrupt]
+fn EUSCI_A0() {
+    with(|cs| {
+        if let Some(ref mut transfer) = *TRANSFER.borrow_ref_mut(cs) {
+            transfer.handle_interrupt();
+        }
+    });
+}
+
+// The compiler will emit calls to the abort() compiler intrinsic if debug assertions are
+// enabled (default for dev profile). MSP430 does not actually have meaningful abort() support
+// so for now, we create our own in each application where debug assertions are present.
+#[no_mangle]
+extern "C" fn abort() -> ! {
+    panic!();
+}
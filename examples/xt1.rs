@@ -0,0 +1,64 @@
+#![no_main]
+#![no_std]
+
+use embedded_hal::digital::v2::*;
+use msp430_rt::entry;
+use msp430fr2x5x_hal::{
+    clock::{ClockConfig, MclkDiv, SmclkDiv, Xt1, Xt1AclkDiv, Xt1Mode},
+    fram::Fram,
+    gpio::Batch,
+    hal::blocking::delay::DelayMs,
+    pmm::Pmm,
+    watchdog::Wdt,
+};
+use panic_msp430 as _;
+
+// Runs the DCO at 16 MHz locked to a 32768 Hz crystal on P2.6/P2.7, which also drives ACLK.
+// Red LED blinks while the crystal is running. Green LED lights up if the crystal has faulted, in
+// which case the clocks run from REFOCLK instead.
+#[entry]
+fn main() -> ! {
+    let periph = msp430fr2355::Peripherals::take().unwrap();
+    let _wdt = Wdt::constrain(periph.WDT_A);
+
+    let pmm = Pmm::new(periph.PMM);
+    let p1 = Batch::new(periph.P1).split(&pmm);
+    let p2 = Batch::new(periph.P2).split(&pmm);
+    let p6 = Batch::new(periph.P6).split(&pmm);
+    let mut red_led = p1.pin0.to_output();
+    let mut green_led = p6.pin6.to_output();
+
+    let mut xt1 = Xt1::crystal(
+        p2.pin7.to_alternate2(),
+        p2.pin6.to_alternate2(),
+        Xt1Mode::LowFrequency,
+        32768,
+    );
+
+    let mut fram = Fram::new(periph.FRCTL);
+    let (_smclk, _aclk, mut delay) = ClockConfig::new(periph.CS)
+        .fll_ref_xt1(&xt1)
+        .aclk_xt1(&xt1, Xt1AclkDiv::_1)
+        .mclk_dcoclk_freq(16_000_000, MclkDiv::_1)
+        .smclk_on(SmclkDiv::_1)
+        .freeze(&mut fram);
+
+    loop {
+        if xt1.is_faulted() && !xt1.clear_fault() {
+            green_led.set_high().ok();
+            red_led.set_low().ok();
+        } else {
+            green_led.set_low().ok();
+            red_led.toggle().ok();
+        }
+        delay.delay_ms(500);
+    }
+}
+
+// The compiler will emit calls to the abort() compiler intrinsic if debug assertions are
+// enabled (default for dev profile). MSP430 does not actually have meaningful abort() support
+// so for now, we create our own in each application where debug assertions are present.
+#[no_mangle]
+extern "C" fn abort() -> ! {
+    panic!();
+}
@@ -0,0 +1,23 @@
+//! Host-side tests for the drivers in msp430fr2x5x-hal that don't touch the hardware directly.
+//!
+//! The HAL can only be built for MSP430, so the driver sources are compiled into this crate
+//! instead and tested against simulated devices in `tests/`. Run with `cargo test` from this
+//! directory.
+
+#[path = "../../src/sdcard.rs"]
+pub mod sdcard;
+
+#[path = "../../src/spi_nor.rs"]
+pub mod spi_nor;
+
+#[path = "../../src/modbus.rs"]
+pub mod modbus;
+
+#[path = "../../src/lin/frame.rs"]
+pub mod lin;
+
+#[path = "../../src/serial/baud.rs"]
+pub mod baud;
+
+#[path = "../../src/serial/timing.rs"]
+pub mod serial;
@@ -0,0 +1,97 @@
+use core::num::NonZeroU32;
+use msp430fr2x5x_hal_host_tests::baud::{
+    baud_rate_error, calculate_baud_config, frame_baud_rate_error,
+};
+
+// (BRCLK, baud rate, UCOS16, UCBRx, UCBRFx, UCBRSx) from the commonly used baud rate settings
+// table in the user's guide. UCBRFx is only checked in oversampling mode.
+const SETTINGS_TABLE: [(u32, u32, bool, u16, u8, u8); 15] = [
+    (32768, 2400, false, 13, 0, 0xB6),
+    (32768, 4800, false, 6, 0, 0xEE),
+    (32768, 9600, false, 3, 0, 0x92),
+    (1_000_000, 9600, true, 6, 8, 0x20),
+    (1_000_000, 19200, true, 3, 4, 0x02),
+    (1_000_000, 38400, true, 1, 10, 0x00),
+    (1_000_000, 57600, true, 1, 1, 0x4A),
+    (1_000_000, 115200, false, 8, 0, 0xD6),
+    (1_048_576, 9600, true, 6, 13, 0x22),
+    (4_000_000, 9600, true, 26, 0, 0xB6),
+    (4_000_000, 115200, true, 2, 2, 0xBB),
+    (8_000_000, 115200, true, 4, 5, 0x55),
+    (16_000_000, 9600, true, 104, 2, 0xB6),
+    (16_000_000, 115200, true, 8, 10, 0xF7),
+    (24_000_000, 115200, true, 13, 0, 0x25),
+];
+
+// (BRCLK, baud rate, TX error, RX error) in hundredths of a percent. The magnitudes are the
+// worst-case errors from the same table. The table counts an early edge as a positive error,
+// the driver as a negative one.
+const ERROR_TABLE: [(u32, u32, i32, i32); 4] = [
+    (1_000_000, 9600, -64, -104),
+    (1_000_000, 19200, -96, -184),
+    (1_000_000, 38400, -160, -344),
+    (8_000_000, 115200, 80, 112),
+];
+
+fn bps(baudrate: u32) -> NonZeroU32 {
+    NonZeroU32::new(baudrate).unwrap()
+}
+
+#[test]
+fn matches_the_baud_rate_settings_table() {
+    for (clk, baud, ucos16, br, brf, brs) in SETTINGS_TABLE {
+        let config = calculate_baud_config(clk, bps(baud));
+        assert_eq!(config.ucos16, ucos16, "{} Hz, {} Bd", clk, baud);
+        assert_eq!(config.br, br, "{} Hz, {} Bd", clk, baud);
+        assert_eq!(config.brs, brs, "{} Hz, {} Bd", clk, baud);
+        if ucos16 {
+            assert_eq!(config.brf, brf, "{} Hz, {} Bd", clk, baud);
+        }
+    }
+}
+
+#[test]
+fn matches_the_bit_timing_error_table() {
+    for (clk, baud, tx_error, rx_error) in ERROR_TABLE {
+        let error = baud_rate_error(clk, baud);
+        assert_eq!(error.tx_error, tx_error, "{} Hz, {} Bd", clk, baud);
+        assert_eq!(error.rx_error, rx_error, "{} Hz, {} Bd", clk, baud);
+    }
+    // Low-frequency mode is only checked for transmit, the table's receive figures assume a
+    // different sample point
+    assert_eq!(baud_rate_error(32768, 9600).tx_error, 1718);
+    assert_eq!(baud_rate_error(1_000_000, 115200).tx_error, 736);
+}
+
+#[test]
+fn reports_the_achieved_baud_rate() {
+    assert_eq!(baud_rate_error(1_000_000, 9600).achieved_baudrate, 9603);
+    assert_eq!(
+        baud_rate_error(16_000_000, 115200).achieved_baudrate,
+        115211
+    );
+}
+
+#[test]
+fn checks_the_error_against_a_limit() {
+    assert!(baud_rate_error(16_000_000, 9600).is_within(10));
+    assert!(baud_rate_error(8_000_000, 115200).is_within(200));
+    assert!(!baud_rate_error(8_000_000, 115200).is_within(100));
+    // Too slow a clock for the baud rate, or no clock at all
+    assert!(!baud_rate_error(32768, 115200).is_within(1000));
+    assert!(!baud_rate_error(0, 9600).is_within(1000));
+}
+
+#[test]
+fn accounts_for_the_frame_length() {
+    for (clk, baud, _, _) in ERROR_TABLE {
+        let error = baud_rate_error(clk, baud);
+        let same = frame_baud_rate_error(clk, baud, 10);
+        assert_eq!(same.tx_error, error.tx_error);
+        assert_eq!(same.rx_error, error.rx_error);
+    }
+    // The error accumulates with every bit when there is no modulation
+    let longer = frame_baud_rate_error(1_000_000, 38400, 11);
+    assert_eq!(longer.tx_error, -176);
+    assert_eq!(longer.rx_error, -360);
+}
@@ -0,0 +1,76 @@
+use msp430fr2x5x_hal_host_tests::lin::{checksum, frame_id, protected_id, ChecksumType};
+
+// Frame IDs and protected IDs from the table in the LIN 2.2A specification
+const PID_TABLE: [(u8, u8); 8] = [
+    (0x00, 0x80),
+    (0x01, 0xC1),
+    (0x02, 0x42),
+    (0x03, 0x03),
+    (0x10, 0x50),
+    (0x3C, 0x3C),
+    (0x3D, 0x7D),
+    (0x3F, 0xBF),
+];
+
+#[test]
+fn computes_protected_ids() {
+    for (id, pid) in PID_TABLE {
+        assert_eq!(protected_id(id), pid, "ID {:#04x}", id);
+    }
+    // The top two bits of the ID are ignored
+    assert_eq!(protected_id(0xC1), 0xC1);
+}
+
+#[test]
+fn checks_protected_id_parity() {
+    for (id, pid) in PID_TABLE {
+        assert_eq!(frame_id(pid), Some(id));
+    }
+    // Exactly one PID per frame ID has valid parity
+    for id in 0..0x40 {
+        let valid = (0..4u8)
+            .filter(|parity| frame_id(id | parity << 6).is_some())
+            .count();
+        assert_eq!(valid, 1, "ID {:#04x}", id);
+    }
+    assert_eq!(frame_id(0x41), None);
+}
+
+#[test]
+fn computes_classic_checksums() {
+    // Example from the LIN 2.2A specification: the sum with carries added back in is 0x19
+    assert_eq!(
+        checksum(ChecksumType::Classic, 0x00, &[0x4A, 0x55, 0x93, 0xE5]),
+        0xE6
+    );
+    assert_eq!(
+        checksum(ChecksumType::Classic, 0x00, &[0x55, 0x93, 0xE5]),
+        0x31
+    );
+    assert_eq!(checksum(ChecksumType::Classic, 0x00, &[0xFF, 0xFF]), 0x00);
+    assert_eq!(checksum(ChecksumType::Classic, 0x00, &[]), 0xFF);
+    // The ID doesn't take part in the classic checksum
+    assert_eq!(
+        checksum(ChecksumType::Classic, 0x10, &[0x01, 0x02]),
+        checksum(ChecksumType::Classic, 0x20, &[0x01, 0x02])
+    );
+}
+
+#[test]
+fn computes_enhanced_checksums() {
+    // PID 0x50 + 0x01 + 0x02 = 0x53
+    assert_eq!(checksum(ChecksumType::Enhanced, 0x10, &[0x01, 0x02]), 0xAC);
+    // Carries are added back in: PID 0xC1 + 0xFF = 0x1C0, then 0xC1
+    assert_eq!(checksum(ChecksumType::Enhanced, 0x01, &[0xFF]), 0x3E);
+}
+
+#[test]
+fn diagnostic_frames_use_classic_checksum() {
+    for id in [0x3C, 0x3D] {
+        assert_eq!(
+            checksum(ChecksumType::Enhanced, id, &[0x01, 0x02]),
+            checksum(ChecksumType::Classic, id, &[0x01, 0x02])
+        );
+    }
+    assert_eq!(checksum(ChecksumType::Enhanced, 0x3C, &[0x01, 0x02]), 0xFC);
+}
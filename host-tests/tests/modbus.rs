@@ -0,0 +1,275 @@
+use msp430fr2x5x_hal_host_tests::modbus::{
+    crc16, frame_gap_ticks, process_request, Exception, FrameReceiver, ModbusMap, ModbusSlave,
+    MAX_FRAME_LEN,
+};
+use msp430fr2x5x_hal_host_tests::serial::RxEvent;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+
+const UNIT_ID: u8 = 0x11;
+
+#[derive(Default)]
+struct TestMap {
+    coils: [bool; 32],
+    holding: [u16; 16],
+    reads: u32,
+}
+
+impl ModbusMap for TestMap {
+    fn read_coil(&mut self, addr: u16) -> Result<bool, Exception> {
+        self.reads += 1;
+        self.coils
+            .get(addr as usize)
+            .copied()
+            .ok_or(Exception::IllegalDataAddress)
+    }
+
+    fn read_holding_register(&mut self, addr: u16) -> Result<u16, Exception> {
+        self.reads += 1;
+        self.holding
+            .get(addr as usize)
+            .copied()
+            .ok_or(Exception::IllegalDataAddress)
+    }
+
+    fn write_coil(&mut self, addr: u16, value: bool) -> Result<(), Exception> {
+        *self
+            .coils
+            .get_mut(addr as usize)
+            .ok_or(Exception::IllegalDataAddress)? = value;
+        Ok(())
+    }
+
+    fn write_holding_register(&mut self, addr: u16, value: u16) -> Result<(), Exception> {
+        *self
+            .holding
+            .get_mut(addr as usize)
+            .ok_or(Exception::IllegalDataAddress)? = value;
+        Ok(())
+    }
+}
+
+// Serial receiver fed from a queue of results. Reports `WouldBlock` once the queue is empty.
+#[derive(Default)]
+struct MockRx {
+    events: VecDeque<nb::Result<RxEvent, ()>>,
+}
+
+impl MockRx {
+    fn push_frame(&mut self, frame: &[u8]) {
+        self.events
+            .extend(frame.iter().map(|&b| Ok(RxEvent::Byte(b))));
+        self.events.push_back(Ok(RxEvent::FrameComplete));
+    }
+}
+
+impl FrameReceiver for MockRx {
+    type Error = ();
+
+    fn read(&mut self) -> nb::Result<RxEvent, ()> {
+        self.events
+            .pop_front()
+            .unwrap_or(Err(nb::Error::WouldBlock))
+    }
+}
+
+#[derive(Default)]
+struct MockTx {
+    sent: Vec<u8>,
+    flushed: bool,
+}
+
+impl embedded_io::ErrorType for MockTx {
+    type Error = Infallible;
+}
+
+impl embedded_io::Write for MockTx {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+        self.flushed = false;
+        self.sent.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Infallible> {
+        self.flushed = true;
+        Ok(())
+    }
+}
+
+// Append the CRC to a frame
+fn frame(bytes: &[u8]) -> Vec<u8> {
+    let mut frame = bytes.to_vec();
+    frame.extend_from_slice(&crc16(bytes).to_le_bytes());
+    frame
+}
+
+fn request(map: &mut TestMap, req: &[u8]) -> Option<Vec<u8>> {
+    let mut resp = [0; MAX_FRAME_LEN];
+    process_request(map, UNIT_ID, req, &mut resp).map(|len| resp[..len].to_vec())
+}
+
+#[test]
+fn crc_matches_reference() {
+    // Read holding registers example from the Modbus over serial line specification
+    assert_eq!(
+        crc16(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A]).to_le_bytes(),
+        [0xC5, 0xCD]
+    );
+    assert_eq!(crc16(b"123456789"), 0x4B37);
+}
+
+#[test]
+fn reads_holding_registers() {
+    let mut map = TestMap::default();
+    map.holding[2] = 0x1234;
+    map.holding[3] = 0xABCD;
+    let resp = request(&mut map, &frame(&[UNIT_ID, 0x03, 0x00, 0x02, 0x00, 0x02]));
+    assert_eq!(
+        resp.unwrap(),
+        frame(&[UNIT_ID, 0x03, 0x04, 0x12, 0x34, 0xAB, 0xCD])
+    );
+}
+
+#[test]
+fn reads_coils_lsb_first() {
+    let mut map = TestMap::default();
+    for i in [0, 3, 8, 9] {
+        map.coils[i] = true;
+    }
+    let resp = request(&mut map, &frame(&[UNIT_ID, 0x01, 0x00, 0x00, 0x00, 0x0A]));
+    assert_eq!(resp.unwrap(), frame(&[UNIT_ID, 0x01, 0x02, 0x09, 0x03]));
+}
+
+#[test]
+fn writes_registers_and_coils() {
+    let mut map = TestMap::default();
+
+    let req = frame(&[UNIT_ID, 0x06, 0x00, 0x05, 0xBE, 0xEF]);
+    assert_eq!(request(&mut map, &req).unwrap(), req);
+    assert_eq!(map.holding[5], 0xBEEF);
+
+    let req = frame(&[
+        UNIT_ID, 0x10, 0x00, 0x00, 0x00, 0x02, 0x04, 0x00, 0x01, 0x00, 0x02,
+    ]);
+    assert_eq!(
+        request(&mut map, &req).unwrap(),
+        frame(&[UNIT_ID, 0x10, 0x00, 0x00, 0x00, 0x02])
+    );
+    assert_eq!(map.holding[..2], [1, 2]);
+
+    let req = frame(&[UNIT_ID, 0x05, 0x00, 0x07, 0xFF, 0x00]);
+    assert_eq!(request(&mut map, &req).unwrap(), req);
+    assert!(map.coils[7]);
+
+    let req = frame(&[UNIT_ID, 0x0F, 0x00, 0x08, 0x00, 0x0A, 0x02, 0x05, 0x02]);
+    assert_eq!(
+        request(&mut map, &req).unwrap(),
+        frame(&[UNIT_ID, 0x0F, 0x00, 0x08, 0x00, 0x0A])
+    );
+    let written: Vec<_> = map.coils[8..18].iter().map(|&c| c as u8).collect();
+    assert_eq!(written, [1, 0, 1, 0, 0, 0, 0, 0, 0, 1]);
+}
+
+#[test]
+fn answers_with_exceptions() {
+    let mut map = TestMap::default();
+    // Past the end of the map
+    let resp = request(&mut map, &frame(&[UNIT_ID, 0x03, 0x00, 0x0F, 0x00, 0x02]));
+    assert_eq!(resp.unwrap(), frame(&[UNIT_ID, 0x83, 0x02]));
+    // Not a valid coil value
+    let resp = request(&mut map, &frame(&[UNIT_ID, 0x05, 0x00, 0x00, 0x12, 0x34]));
+    assert_eq!(resp.unwrap(), frame(&[UNIT_ID, 0x85, 0x03]));
+    // Unsupported function
+    let resp = request(&mut map, &frame(&[UNIT_ID, 0x2B, 0x0E, 0x01, 0x00]));
+    assert_eq!(resp.unwrap(), frame(&[UNIT_ID, 0xAB, 0x01]));
+}
+
+#[test]
+fn ignores_bad_frames() {
+    let mut map = TestMap::default();
+    let mut req = frame(&[UNIT_ID, 0x06, 0x00, 0x00, 0x00, 0x01]);
+    req[3] ^= 1;
+    assert_eq!(request(&mut map, &req), None);
+    assert_eq!(
+        request(&mut map, &frame(&[0x12, 0x06, 0x00, 0x00, 0x00, 0x01])),
+        None
+    );
+    assert_eq!(request(&mut map, &[UNIT_ID, 0x06, 0x00]), None);
+    assert_eq!(map.holding[0], 0);
+}
+
+#[test]
+fn executes_broadcast_writes_only() {
+    let mut map = TestMap::default();
+    assert_eq!(
+        request(&mut map, &frame(&[0x00, 0x06, 0x00, 0x01, 0x00, 0x2A])),
+        None
+    );
+    assert_eq!(map.holding[1], 42);
+
+    assert_eq!(
+        request(&mut map, &frame(&[0x00, 0x03, 0x00, 0x00, 0x00, 0x02])),
+        None
+    );
+    assert_eq!(
+        request(&mut map, &frame(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x02])),
+        None
+    );
+    assert_eq!(map.reads, 0);
+}
+
+#[test]
+fn slave_serves_frames_from_serial() {
+    let mut rx = MockRx::default();
+    rx.push_frame(&frame(&[UNIT_ID, 0x06, 0x00, 0x00, 0x12, 0x34]));
+    // Addressed to another slave
+    rx.push_frame(&frame(&[0x12, 0x06, 0x00, 0x00, 0x56, 0x78]));
+    // A corrupted byte drops the whole frame
+    let bad = frame(&[UNIT_ID, 0x06, 0x00, 0x01, 0x56, 0x78]);
+    rx.events.push_back(Ok(RxEvent::Byte(bad[0])));
+    rx.events.push_back(Err(nb::Error::Other(())));
+    rx.push_frame(&bad[1..]);
+    // Longer than the maximum frame
+    rx.push_frame(&[0; MAX_FRAME_LEN + 1]);
+    rx.push_frame(&frame(&[UNIT_ID, 0x03, 0x00, 0x00, 0x00, 0x01]));
+
+    let mut slave = ModbusSlave::new(rx, MockTx::default(), TestMap::default(), UNIT_ID);
+    for _ in 0..5 {
+        slave.poll().unwrap();
+    }
+    assert!(matches!(slave.poll(), Err(nb::Error::WouldBlock)));
+
+    let (_, tx, map) = slave.free();
+    assert_eq!(map.holding[..2], [0x1234, 0]);
+    let mut expected = frame(&[UNIT_ID, 0x06, 0x00, 0x00, 0x12, 0x34]);
+    expected.extend(frame(&[UNIT_ID, 0x03, 0x02, 0x12, 0x34]));
+    assert_eq!(tx.sent, expected);
+    assert!(tx.flushed);
+}
+
+#[test]
+fn slave_waits_for_frame_gap() {
+    let mut rx = MockRx::default();
+    let req = frame(&[UNIT_ID, 0x06, 0x00, 0x00, 0x00, 0x07]);
+    let (head, tail) = req.split_at(3);
+    rx.events.extend(head.iter().map(|&b| Ok(RxEvent::Byte(b))));
+    rx.events.push_back(Err(nb::Error::WouldBlock));
+    rx.events.extend(tail.iter().map(|&b| Ok(RxEvent::Byte(b))));
+    rx.events.push_back(Err(nb::Error::WouldBlock));
+    rx.events.push_back(Ok(RxEvent::FrameComplete));
+
+    let mut slave = ModbusSlave::new(rx, MockTx::default(), TestMap::default(), UNIT_ID);
+    assert!(matches!(slave.poll(), Err(nb::Error::WouldBlock)));
+    assert!(matches!(slave.poll(), Err(nb::Error::WouldBlock)));
+    assert_eq!(slave.map().holding[0], 0);
+    slave.poll().unwrap();
+    assert_eq!(slave.map().holding[0], 7);
+}
+
+#[test]
+fn frame_gap_uses_fixed_time_above_19200_baud() {
+    // 3.5 characters of 11 bits at 9600 baud is 4.01 ms
+    assert_eq!(frame_gap_ticks(1_000_000, 9600), 4010);
+    assert_eq!(frame_gap_ticks(1_000_000, 38400), 1750);
+    assert_eq!(frame_gap_ticks(1_000_000, 115_200), 1750);
+}
@@ -0,0 +1,448 @@
+use embedded_hal_1::spi::{ErrorType, Operation, SpiDevice};
+use msp430fr2x5x_hal_host_tests::sdcard::{
+    BlockDevice, CardType, SdCard, SdCardError, BLOCK_SIZE,
+};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+
+const NUM_BLOCKS: usize = 8;
+// ACMD41/CMD1 polls that return "idle" before the card reports ready
+const INIT_POLLS: u32 = 3;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Mmc,
+    SdV1,
+    SdV2,
+    Sdhc,
+}
+
+enum WriteState {
+    // Not in a write command
+    None,
+    // Waiting for a start token, or the stop token in a multi-block write
+    Token { multi: bool },
+    // Receiving a data block and its CRC
+    Data { multi: bool, buf: Vec<u8> },
+}
+
+// Byte level model of an SD card in SPI mode
+struct MockCard {
+    kind: Kind,
+    blocks: Vec<[u8; BLOCK_SIZE]>,
+    csd: [u8; 16],
+    out: VecDeque<u8>,
+    frame: Vec<u8>,
+    idle: bool,
+    app_cmd: bool,
+    init_polls: u32,
+    write: WriteState,
+    write_block: usize,
+    // Next block of a multi-block read
+    streaming: Option<usize>,
+    corrupt_read_crc: bool,
+    bad_command_crcs: u32,
+}
+
+impl MockCard {
+    fn new(kind: Kind) -> Self {
+        let mut blocks = vec![[0; BLOCK_SIZE]; NUM_BLOCKS];
+        for (i, block) in blocks.iter_mut().enumerate() {
+            for (j, byte) in block.iter_mut().enumerate() {
+                *byte = (i * 31 + j) as u8;
+            }
+        }
+        MockCard {
+            kind,
+            blocks,
+            csd: csd_v2(NUM_BLOCKS as u32 / 1024),
+            out: VecDeque::new(),
+            frame: Vec::new(),
+            idle: true,
+            app_cmd: false,
+            init_polls: 0,
+            write: WriteState::None,
+            write_block: 0,
+            streaming: None,
+            corrupt_read_crc: false,
+            bad_command_crcs: 0,
+        }
+    }
+
+    fn r1(&self) -> u8 {
+        self.idle as u8
+    }
+
+    fn block_index(&self, arg: u32) -> usize {
+        match self.kind {
+            Kind::Sdhc => arg as usize,
+            _ => {
+                assert_eq!(arg as usize % BLOCK_SIZE, 0, "unaligned byte address");
+                arg as usize / BLOCK_SIZE
+            }
+        }
+    }
+
+    fn push_data(&mut self, data: &[u8]) {
+        let mut crc = crc16(data);
+        if self.corrupt_read_crc {
+            crc ^= 1;
+        }
+        // Access time before the start token
+        self.out.extend([0xFF, 0xFF, 0xFE]);
+        self.out.extend(data);
+        self.out.extend(crc.to_be_bytes());
+    }
+
+    // Byte clocked out by the card while the host reads
+    fn miso(&mut self) -> u8 {
+        if self.out.is_empty() {
+            if let Some(block) = self.streaming {
+                let data = self.blocks[block];
+                self.push_data(&data);
+                self.streaming = Some(block + 1);
+            }
+        }
+        self.out.pop_front().unwrap_or(0xFF)
+    }
+
+    // Byte clocked in from the host
+    fn mosi(&mut self, byte: u8) {
+        match std::mem::replace(&mut self.write, WriteState::None) {
+            WriteState::Token { multi } => {
+                self.write = match byte {
+                    0xFE | 0xFC => WriteState::Data {
+                        multi,
+                        buf: Vec::new(),
+                    },
+                    0xFD if multi => {
+                        // Busy while programming
+                        self.out.extend([0x00, 0x00]);
+                        WriteState::None
+                    }
+                    _ => WriteState::Token { multi },
+                };
+                return;
+            }
+            WriteState::Data { multi, mut buf } => {
+                buf.push(byte);
+                if buf.len() < BLOCK_SIZE + 2 {
+                    self.write = WriteState::Data { multi, buf };
+                    return;
+                }
+                let crc = u16::from_be_bytes([buf[BLOCK_SIZE], buf[BLOCK_SIZE + 1]]);
+                if crc == crc16(&buf[..BLOCK_SIZE]) {
+                    self.blocks[self.write_block].copy_from_slice(&buf[..BLOCK_SIZE]);
+                    self.write_block += 1;
+                    self.out.extend([0x05, 0x00, 0x00]);
+                } else {
+                    self.out.extend([0x0B]);
+                }
+                if multi {
+                    self.write = WriteState::Token { multi };
+                }
+                return;
+            }
+            WriteState::None => (),
+        }
+
+        if self.frame.is_empty() && byte & 0xC0 != 0x40 {
+            return;
+        }
+        self.frame.push(byte);
+        if self.frame.len() == 6 {
+            let frame = std::mem::take(&mut self.frame);
+            self.command(&frame);
+        }
+    }
+
+    fn command(&mut self, frame: &[u8]) {
+        if frame[5] != (crc7(&frame[..5]) << 1) | 1 {
+            self.bad_command_crcs += 1;
+            // Command CRC error
+            self.out.extend([0xFF, self.r1() | 0x08]);
+            return;
+        }
+        let cmd = frame[0] & 0x3F;
+        let arg = u32::from_be_bytes([frame[1], frame[2], frame[3], frame[4]]);
+        let app_cmd = std::mem::replace(&mut self.app_cmd, false);
+        let sd = self.kind != Kind::Mmc;
+
+        // Response time before R1
+        self.out.push_back(0xFF);
+        match (app_cmd, cmd) {
+            (_, 0) => {
+                self.idle = true;
+                self.init_polls = 0;
+                self.out.push_back(self.r1());
+            }
+            (_, 8) if matches!(self.kind, Kind::SdV2 | Kind::Sdhc) => {
+                self.out.push_back(self.r1());
+                self.out.extend([0x00, 0x00, (arg >> 8) as u8 & 0x0F, arg as u8]);
+            }
+            (_, 55) if sd => {
+                self.app_cmd = true;
+                self.out.push_back(self.r1());
+            }
+            (true, 41) | (false, 1) => {
+                self.init_polls += 1;
+                if self.init_polls > INIT_POLLS {
+                    self.idle = false;
+                }
+                self.out.push_back(self.r1());
+            }
+            (_, 58) => {
+                self.out.push_back(self.r1());
+                let ccs = if self.kind == Kind::Sdhc { 0xC0 } else { 0x80 };
+                self.out.extend([ccs, 0xFF, 0x80, 0x00]);
+            }
+            (_, 59) | (_, 16) => self.out.push_back(self.r1()),
+            (_, 9) => {
+                self.out.push_back(self.r1());
+                let csd = self.csd;
+                self.push_data(&csd);
+            }
+            (_, 17) => {
+                self.out.push_back(self.r1());
+                let data = self.blocks[self.block_index(arg)];
+                self.push_data(&data);
+            }
+            (_, 18) => {
+                self.out.push_back(self.r1());
+                self.streaming = Some(self.block_index(arg));
+            }
+            (_, 12) => {
+                self.streaming = None;
+                // Stuff byte, then R1
+                self.out.clear();
+                self.out.extend([0xFF, 0xFF, self.r1()]);
+            }
+            (_, 24) | (_, 25) => {
+                self.out.push_back(self.r1());
+                self.write_block = self.block_index(arg);
+                self.write = WriteState::Token { multi: cmd == 25 };
+            }
+            // Illegal command
+            _ => self.out.push_back(self.r1() | 0x04),
+        }
+    }
+}
+
+impl ErrorType for MockCard {
+    type Error = Infallible;
+}
+
+impl SpiDevice for MockCard {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+        for op in operations {
+            match op {
+                Operation::Read(buf) => {
+                    for b in buf.iter_mut() {
+                        *b = self.miso();
+                        self.mosi(0xFF);
+                    }
+                }
+                Operation::Write(buf) => buf.iter().for_each(|&b| self.mosi(b)),
+                Operation::Transfer(read, write) => {
+                    for i in 0..read.len().max(write.len()) {
+                        let miso = self.miso();
+                        self.mosi(write.get(i).copied().unwrap_or(0xFF));
+                        if let Some(r) = read.get_mut(i) {
+                            *r = miso;
+                        }
+                    }
+                }
+                Operation::TransferInPlace(buf) => {
+                    for b in buf.iter_mut() {
+                        let miso = self.miso();
+                        self.mosi(*b);
+                        *b = miso;
+                    }
+                }
+                Operation::DelayNs(_) => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+// Reference implementations, written independently of the driver's
+fn crc7(data: &[u8]) -> u8 {
+    let mut crc = 0u32;
+    for bit in data.iter().flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1)) {
+        crc = (crc << 1) | bit as u32;
+        if crc & 0x80 != 0 {
+            crc ^= 0x89;
+        }
+    }
+    for _ in 0..7 {
+        crc <<= 1;
+        if crc & 0x80 != 0 {
+            crc ^= 0x89;
+        }
+    }
+    crc as u8
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &b in data {
+        for i in (0..8).rev() {
+            let bit = ((b >> i) & 1) as u16;
+            let top = crc >> 15;
+            crc <<= 1;
+            if top ^ bit != 0 {
+                crc ^= 0x1021;
+            }
+        }
+    }
+    crc
+}
+
+// CSD version 2.0 for a card with `c_size + 1` 512 KiB units
+fn csd_v2(c_size: u32) -> [u8; 16] {
+    let mut csd = [0; 16];
+    csd[0] = 0x40;
+    csd[7] = (c_size >> 16) as u8 & 0x3F;
+    csd[8] = (c_size >> 8) as u8;
+    csd[9] = c_size as u8;
+    csd
+}
+
+// CSD version 1.0
+fn csd_v1(read_bl_len: u8, c_size: u16, c_size_mult: u8) -> [u8; 16] {
+    let mut csd = [0; 16];
+    csd[5] = read_bl_len & 0x0F;
+    csd[6] = (c_size >> 10) as u8 & 0x03;
+    csd[7] = (c_size >> 2) as u8;
+    csd[8] = (c_size as u8 & 0x03) << 6;
+    csd[9] = (c_size_mult >> 1) & 0x03;
+    csd[10] = (c_size_mult & 0x01) << 7;
+    csd
+}
+
+fn init(kind: Kind) -> SdCard<MockCard> {
+    let mut card = SdCard::new(MockCard::new(kind));
+    card.init().unwrap();
+    card
+}
+
+#[test]
+fn command_crcs_match_known_values() {
+    // CMD0 and CMD8 frames with their well known CRC bytes
+    assert_eq!((crc7(&[0x40, 0, 0, 0, 0]) << 1) | 1, 0x95);
+    assert_eq!((crc7(&[0x48, 0, 0, 0x01, 0xAA]) << 1) | 1, 0x87);
+    assert_eq!(crc16(&[0xFF; 512]), 0x7FA1);
+
+    let card = init(Kind::Sdhc);
+    assert_eq!(card.free().bad_command_crcs, 0);
+}
+
+#[test]
+fn detects_card_types() {
+    for kind in [Kind::Mmc, Kind::SdV1, Kind::SdV2, Kind::Sdhc] {
+        let mut card = SdCard::new(MockCard::new(kind));
+        let expected = match kind {
+            Kind::Mmc => CardType::Mmc,
+            Kind::SdV1 => CardType::SdV1,
+            Kind::SdV2 => CardType::SdV2,
+            Kind::Sdhc => CardType::Sdhc,
+        };
+        assert_eq!(card.init().unwrap(), expected);
+        assert_eq!(card.card_type(), Some(expected));
+    }
+}
+
+#[test]
+fn requires_init() {
+    let mut card = SdCard::new(MockCard::new(Kind::Sdhc));
+    let mut block = [0; BLOCK_SIZE];
+    assert!(matches!(
+        card.read_block(0, &mut block),
+        Err(SdCardError::NotInitialized)
+    ));
+    assert!(matches!(card.num_blocks(), Err(SdCardError::NotInitialized)));
+}
+
+#[test]
+fn reads_single_block() {
+    for kind in [Kind::SdV1, Kind::Sdhc] {
+        let mut card = init(kind);
+        let mut block = [0; BLOCK_SIZE];
+        card.read_block(3, &mut block).unwrap();
+        assert_eq!(block, card.device_mut().blocks[3]);
+    }
+}
+
+#[test]
+fn reads_multiple_blocks() {
+    for kind in [Kind::SdV2, Kind::Sdhc] {
+        let mut card = init(kind);
+        let mut blocks = [[0; BLOCK_SIZE]; 3];
+        card.read_blocks(2, &mut blocks).unwrap();
+        assert_eq!(blocks[..], card.device_mut().blocks[2..5]);
+        // The card must be back in a state that accepts commands
+        let mut block = [0; BLOCK_SIZE];
+        card.read_block(0, &mut block).unwrap();
+        assert_eq!(block, card.device_mut().blocks[0]);
+    }
+}
+
+#[test]
+fn writes_blocks() {
+    for kind in [Kind::Mmc, Kind::Sdhc] {
+        let mut card = init(kind);
+        let single = [0xA5; BLOCK_SIZE];
+        card.write_block(1, &single).unwrap();
+        assert_eq!(card.device_mut().blocks[1], single);
+
+        let multi = [[0x11; BLOCK_SIZE], [0x22; BLOCK_SIZE], [0x33; BLOCK_SIZE]];
+        BlockDevice::write_blocks(&mut card, 4, &multi).unwrap();
+        assert_eq!(card.device_mut().blocks[4..7], multi[..]);
+
+        let mut back = [[0; BLOCK_SIZE]; 3];
+        BlockDevice::read_blocks(&mut card, 4, &mut back).unwrap();
+        assert_eq!(back, multi);
+    }
+}
+
+#[test]
+fn reports_read_crc_errors() {
+    let mut card = init(Kind::Sdhc);
+    card.device_mut().corrupt_read_crc = true;
+    let mut block = [0; BLOCK_SIZE];
+    assert!(matches!(
+        card.read_block(0, &mut block),
+        Err(SdCardError::Crc)
+    ));
+}
+
+#[test]
+fn reads_size_from_csd() {
+    let mut card = init(Kind::Sdhc);
+    card.device_mut().csd = csd_v2(7);
+    assert_eq!(card.num_blocks().unwrap(), 8 * 1024);
+
+    // (c_size + 1) * 2^(c_size_mult + 2) blocks of 2^read_bl_len bytes
+    let mut card = init(Kind::SdV1);
+    card.device_mut().csd = csd_v1(10, 4095, 7);
+    assert_eq!(card.num_blocks().unwrap(), 4096 * 512 * 2);
+    card.device_mut().csd = csd_v1(9, 99, 0);
+    assert_eq!(card.num_blocks().unwrap(), 100 * 4);
+}
+
+#[test]
+fn rejects_invalid_csd() {
+    let mut card = init(Kind::SdV1);
+    for read_bl_len in [0, 5, 8, 12, 15] {
+        card.device_mut().csd = csd_v1(read_bl_len, 100, 3);
+        assert!(matches!(card.num_blocks(), Err(SdCardError::InvalidCsd)));
+    }
+
+    // The largest 22-bit C_SIZE overflows a u32 block count
+    let mut card = init(Kind::Sdhc);
+    card.device_mut().csd = csd_v2(0x3F_FFFF);
+    assert!(matches!(card.num_blocks(), Err(SdCardError::InvalidCsd)));
+    card.device_mut().csd = csd_v2(0x3F_FFFE);
+    assert_eq!(card.num_blocks().unwrap(), 0x3F_FFFF * 1024);
+}
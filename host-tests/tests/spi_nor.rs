@@ -0,0 +1,325 @@
+use embedded_hal_1::spi::{ErrorType, Operation, SpiDevice};
+use msp430fr2x5x_hal_host_tests::spi_nor::{
+    EraseSize, JedecId, NorFlashError, SpiNorFlash, PAGE_SIZE,
+};
+use std::convert::Infallible;
+
+// 1 MiB, like a W25Q80
+const FLASH_SIZE: usize = 1024 * 1024;
+const JEDEC_ID: [u8; 3] = [0xEF, 0x40, 0x14];
+const SFDP_TABLE_PTR: usize = 0x30;
+// Status reads that report WIP after each program or erase
+const BUSY_POLLS: u32 = 3;
+
+// Simulated NOR flash. Commands take effect when CS is deasserted at the end of a transaction, and
+// commands other than status reads are ignored while the chip is busy, like real parts.
+struct SimFlash {
+    mem: Vec<u8>,
+    sfdp: Vec<u8>,
+    wel: bool,
+    busy_polls: u32,
+    powered_down: bool,
+    // Bytes clocked in during the current transaction
+    rx: Vec<u8>,
+    status_reads: u32,
+    ignored_commands: u32,
+}
+
+impl SimFlash {
+    fn new() -> Self {
+        let mut sfdp = vec![0xFF; 0x100];
+        sfdp[..4].copy_from_slice(b"SFDP");
+        // Revision 1.6, one parameter header
+        sfdp[4..8].copy_from_slice(&[0x06, 0x01, 0x00, 0xFF]);
+        // Basic flash parameter table, 9 DWORDs long
+        sfdp[8..16].copy_from_slice(&[0x00, 0x06, 0x01, 0x09, SFDP_TABLE_PTR as u8, 0, 0, 0xFF]);
+        let density = (FLASH_SIZE as u32 * 8 - 1).to_le_bytes();
+        sfdp[SFDP_TABLE_PTR + 4..SFDP_TABLE_PTR + 8].copy_from_slice(&density);
+
+        SimFlash {
+            mem: vec![0xFF; FLASH_SIZE],
+            sfdp,
+            wel: false,
+            busy_polls: 0,
+            powered_down: false,
+            rx: Vec::new(),
+            status_reads: 0,
+            ignored_commands: 0,
+        }
+    }
+
+    fn set_density(&mut self, density: u32) {
+        self.sfdp[SFDP_TABLE_PTR + 4..SFDP_TABLE_PTR + 8].copy_from_slice(&density.to_le_bytes());
+    }
+
+    fn addr(&self) -> usize {
+        (self.rx[1] as usize) << 16 | (self.rx[2] as usize) << 8 | self.rx[3] as usize
+    }
+
+    fn status(&self) -> u8 {
+        (self.busy_polls > 0) as u8 | (self.wel as u8) << 1
+    }
+
+    // Exchange one byte
+    fn transfer(&mut self, mosi: u8) -> u8 {
+        let i = self.rx.len();
+        self.rx.push(mosi);
+        if i == 0 || self.powered_down {
+            return 0xFF;
+        }
+        match self.rx[0] {
+            0x05 => {
+                self.status_reads += 1;
+                let status = self.status();
+                self.busy_polls = self.busy_polls.saturating_sub(1);
+                status
+            }
+            _ if self.busy_polls > 0 => 0xFF,
+            0x9F => JEDEC_ID.get(i - 1).copied().unwrap_or(0xFF),
+            0x03 if i >= 4 => self.mem[(self.addr() + i - 4) % FLASH_SIZE],
+            0x5A if i >= 5 => self.sfdp.get(self.addr() + i - 5).copied().unwrap_or(0xFF),
+            _ => 0xFF,
+        }
+    }
+
+    // CS deasserted
+    fn end(&mut self) {
+        let rx = std::mem::take(&mut self.rx);
+        let cmd = match rx.first() {
+            Some(&cmd) => cmd,
+            None => return,
+        };
+        if self.powered_down {
+            if cmd == 0xAB {
+                self.powered_down = false;
+            }
+            return;
+        }
+        if cmd == 0x05 {
+            return;
+        }
+        if self.busy_polls > 0 {
+            self.ignored_commands += 1;
+            return;
+        }
+        self.rx = rx;
+        match self.rx[0] {
+            0x06 => self.wel = true,
+            0xB9 => self.powered_down = true,
+            0x02 if self.wel && self.rx.len() > 4 => {
+                // Programming wraps around within the page, as on real chips
+                let addr = self.addr() % FLASH_SIZE;
+                let page = addr & !(PAGE_SIZE as usize - 1);
+                for (i, &byte) in self.rx[4..].iter().enumerate() {
+                    let offset = (addr + i) % PAGE_SIZE as usize;
+                    self.mem[page + offset] &= byte;
+                }
+                self.finish_write();
+            }
+            cmd @ (0x20 | 0x52 | 0xD8) if self.wel && self.rx.len() == 4 => {
+                let size = match cmd {
+                    0x20 => 4 * 1024,
+                    0x52 => 32 * 1024,
+                    _ => 64 * 1024,
+                };
+                let start = (self.addr() % FLASH_SIZE) & !(size - 1);
+                self.mem[start..start + size].fill(0xFF);
+                self.finish_write();
+            }
+            0xC7 if self.wel => {
+                self.mem.fill(0xFF);
+                self.finish_write();
+            }
+            _ => (),
+        }
+        self.rx.clear();
+    }
+
+    fn finish_write(&mut self) {
+        self.wel = false;
+        self.busy_polls = BUSY_POLLS;
+    }
+}
+
+impl ErrorType for SimFlash {
+    type Error = Infallible;
+}
+
+impl SpiDevice for SimFlash {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+        for op in operations {
+            match op {
+                Operation::Read(buf) => buf.iter_mut().for_each(|b| *b = self.transfer(0)),
+                Operation::Write(buf) => buf.iter().for_each(|&b| {
+                    self.transfer(b);
+                }),
+                Operation::Transfer(read, write) => {
+                    for i in 0..read.len().max(write.len()) {
+                        let miso = self.transfer(write.get(i).copied().unwrap_or(0));
+                        if let Some(r) = read.get_mut(i) {
+                            *r = miso;
+                        }
+                    }
+                }
+                Operation::TransferInPlace(buf) => {
+                    buf.iter_mut().for_each(|b| *b = self.transfer(*b))
+                }
+                Operation::DelayNs(_) => (),
+            }
+        }
+        self.end();
+        Ok(())
+    }
+}
+
+fn flash() -> SpiNorFlash<SimFlash> {
+    SpiNorFlash::new(SimFlash::new())
+}
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 + 3) as u8).collect()
+}
+
+#[test]
+fn reads_jedec_id() {
+    assert_eq!(
+        flash().read_jedec_id().unwrap(),
+        JedecId {
+            manufacturer: 0xEF,
+            memory_type: 0x40,
+            capacity: 0x14,
+        }
+    );
+}
+
+#[test]
+fn reads_sfdp() {
+    let mut flash = flash();
+    let info = flash.read_sfdp_info().unwrap();
+    assert_eq!((info.major_rev, info.minor_rev), (1, 6));
+    assert_eq!(info.capacity, FLASH_SIZE as u32);
+
+    // Densities of 4 Gbit and up are given as a power of two
+    let mut sim = flash.free();
+    sim.set_density(0x8000_0000 | 32);
+    let mut flash = SpiNorFlash::new(sim);
+    assert_eq!(flash.read_sfdp_info().unwrap().capacity, 512 * 1024 * 1024);
+
+    let mut sim = flash.free();
+    sim.set_density(0x8000_0000 | 40);
+    let mut flash = SpiNorFlash::new(sim);
+    assert!(matches!(
+        flash.read_sfdp_info(),
+        Err(NorFlashError::InvalidSfdp)
+    ));
+
+    let mut sim = flash.free();
+    sim.sfdp[0] = 0;
+    let mut flash = SpiNorFlash::new(sim);
+    assert!(matches!(
+        flash.read_sfdp_info(),
+        Err(NorFlashError::InvalidSfdp)
+    ));
+}
+
+#[test]
+fn reads_data() {
+    let mut sim = SimFlash::new();
+    let data = pattern(1000);
+    sim.mem[0x1234..0x1234 + 1000].copy_from_slice(&data);
+    let mut flash = SpiNorFlash::new(sim);
+
+    let mut buf = vec![0; 1000];
+    flash.read(0x1234, &mut buf).unwrap();
+    assert_eq!(buf, data);
+}
+
+#[test]
+fn splits_writes_at_page_boundaries() {
+    let mut flash = flash();
+    let data = pattern(700);
+    let addr = 3 * PAGE_SIZE - 10;
+    flash.write(addr, &data).unwrap();
+
+    let mut buf = vec![0; 700];
+    flash.read(addr, &mut buf).unwrap();
+    assert_eq!(buf, data);
+
+    let sim = flash.free();
+    // Nothing outside the written range was touched
+    assert!(sim.mem[..addr as usize].iter().all(|&b| b == 0xFF));
+    assert!(sim.mem[addr as usize + 700..].iter().all(|&b| b == 0xFF));
+    // Each page program was waited for, so no command was sent while the chip was busy
+    assert_eq!(sim.ignored_commands, 0);
+    assert!(sim.status_reads >= 4 * (BUSY_POLLS + 1));
+}
+
+#[test]
+fn erases_aligned_regions() {
+    let mut flash = flash();
+    let data = vec![0u8; 256 * 1024];
+    for (i, chunk) in data.chunks(4096).enumerate() {
+        flash.write((i * 4096) as u32, chunk).unwrap();
+    }
+
+    flash.erase(0x1000, EraseSize::Sector4K).unwrap();
+    flash.erase(0x8000, EraseSize::Block32K).unwrap();
+    flash.erase(0x20000, EraseSize::Block64K).unwrap();
+
+    let sim = flash.free();
+    let erased = |range: std::ops::Range<usize>| sim.mem[range].iter().all(|&b| b == 0xFF);
+    let zeroed = |range: std::ops::Range<usize>| sim.mem[range].iter().all(|&b| b == 0x00);
+    assert!(zeroed(0..0x1000));
+    assert!(erased(0x1000..0x2000));
+    assert!(zeroed(0x2000..0x8000));
+    assert!(erased(0x8000..0x10000));
+    assert!(zeroed(0x10000..0x20000));
+    assert!(erased(0x20000..0x30000));
+    assert!(zeroed(0x30000..0x40000));
+    assert_eq!(sim.ignored_commands, 0);
+}
+
+#[test]
+fn rejects_unaligned_erase_and_bad_addresses() {
+    let mut flash = flash();
+    assert!(matches!(
+        flash.erase(0x1800, EraseSize::Sector4K),
+        Err(NorFlashError::NotAligned)
+    ));
+    assert!(matches!(
+        flash.erase(0x1000, EraseSize::Block32K),
+        Err(NorFlashError::NotAligned)
+    ));
+    assert!(matches!(
+        flash.read(0x0100_0000, &mut [0; 4]),
+        Err(NorFlashError::OutOfBounds)
+    ));
+    assert!(matches!(
+        flash.write(0x00FF_FFFE, &[0; 4]),
+        Err(NorFlashError::OutOfBounds)
+    ));
+    assert!(matches!(
+        flash.read(0x00FF_FFFF, &mut [0; 2]),
+        Err(NorFlashError::OutOfBounds)
+    ));
+    // Rejected accesses never reach the chip
+    assert!(flash.free().mem.iter().all(|&b| b == 0xFF));
+}
+
+#[test]
+fn erases_chip() {
+    let mut flash = flash();
+    flash.write(0x500, &pattern(300)).unwrap();
+    flash.erase_chip().unwrap();
+    assert!(!flash.is_busy().unwrap());
+    assert!(flash.free().mem.iter().all(|&b| b == 0xFF));
+}
+
+#[test]
+fn powers_down_and_wakes_up() {
+    let mut flash = flash();
+    flash.power_down().unwrap();
+    assert_ne!(flash.read_jedec_id().unwrap().manufacturer, 0xEF);
+    flash.wake_up().unwrap();
+    assert_eq!(flash.read_jedec_id().unwrap().manufacturer, 0xEF);
+}
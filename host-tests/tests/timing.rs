@@ -0,0 +1,18 @@
+use msp430fr2x5x_hal_host_tests::serial::char_times_to_ticks;
+
+#[test]
+fn converts_character_times_to_ticks() {
+    // 3.5 characters of 11 bits at 9600 baud is 4.01 ms
+    assert_eq!(char_times_to_ticks(1_000_000, 9600, 11, 35), 4010);
+    assert_eq!(char_times_to_ticks(32768, 19200, 11, 35), 65);
+    // One 10 bit character at 9600 baud from ACLK
+    assert_eq!(char_times_to_ticks(32768, 9600, 10, 10), 34);
+}
+
+#[test]
+fn saturates_long_gaps() {
+    assert_eq!(char_times_to_ticks(16_000_000, 300, 11, 35), u16::MAX);
+    // A zero baud rate is treated as 1 baud rather than dividing by zero
+    assert_eq!(char_times_to_ticks(32768, 0, 10, 10), u16::MAX);
+    assert_eq!(char_times_to_ticks(1000, 0, 10, 10), 10_000);
+}
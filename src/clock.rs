@@ -5,17 +5,25 @@
 //! Configuration of MCLK and SMCLK *must* occur, though SMCLK can be disabled. In that case, only
 //! `Aclk` is returned.
 //!
-//! DCO with FLL is supported on MCLK for select frequencies. Supporting arbitrary frequencies on
-//! the DCO requires complex calibration routines not supported by the HAL.
+//! DCO with FLL is supported on MCLK, either at select frequencies using the factory trim, or at
+//! any multiple of REFOCLK from about 1 to 24 MHz using the software trim routine from TI's
+//! reference code.
+//!
+//! An external crystal or clock on XT1 can be used as the source of ACLK or MCLK, or as the FLL
+//! reference in place of REFOCLK. If XT1 faults, the hardware switches those clocks to REFOCLK
+//! until the fault is cleared, which can be checked with `Xt1::is_faulted()`.
 
 use core::arch::asm;
 
 use crate::delay::Delay;
 use crate::fram::{Fram, WaitStates};
+use crate::gpio::{Alternate2, Pin, Pin6, Pin7, P2};
 use msp430fr2355 as pac;
 use pac::cs::csctl1::DCORSEL_A;
+use pac::cs::csctl3::{FLLREFDIV_A, SELREF_A};
 use pac::cs::csctl4::{SELA_A, SELMS_A};
 pub use pac::cs::csctl5::{DIVM_A as MclkDiv, DIVS_A as SmclkDiv};
+use pac::cs::csctl6::{DIVA_A, XT1DRIVE_A, XT1HFFREQ_A};
 
 /// REFOCLK frequency
 pub const REFOCLK: u16 = 32768;
@@ -26,15 +34,23 @@ enum MclkSel {
     Refoclk,
     Vloclk,
     Dcoclk(DcoclkFreqSel),
+    // Target frequency, with software trim
+    DcoclkTrimmed(u32),
+    // XT1 frequency
+    Xt1(u32),
 }
 
 impl MclkSel {
     #[inline]
-    fn freq(&self) -> u32 {
+    fn freq(&self, fll_ref_freq: u32) -> u32 {
         match self {
             MclkSel::Vloclk => VLOCLK as u32,
             MclkSel::Refoclk => REFOCLK as u32,
-            MclkSel::Dcoclk(sel) => sel.freq(),
+            MclkSel::Dcoclk(sel) => sel.multiplier() as u32 * fll_ref_freq,
+            MclkSel::DcoclkTrimmed(target_freq) => {
+                dco_multiplier(*target_freq, fll_ref_freq) as u32 * fll_ref_freq
+            }
+            MclkSel::Xt1(freq) => *freq,
         }
     }
 
@@ -43,15 +59,72 @@ impl MclkSel {
         match self {
             MclkSel::Vloclk => SELMS_A::VLOCLK,
             MclkSel::Refoclk => SELMS_A::REFOCLK,
-            MclkSel::Dcoclk(_) => SELMS_A::DCOCLKDIV,
+            MclkSel::Dcoclk(_) | MclkSel::DcoclkTrimmed(_) => SELMS_A::DCOCLKDIV,
+            MclkSel::Xt1(_) => SELMS_A::XT1CLK,
         }
     }
 }
 
+/// Smallest FLL multiplier accepted by `ClockConfig::mclk_dcoclk_freq()` with a 32768 Hz FLL
+/// reference, giving about 1 MHz
+pub const DCO_MULTIPLIER_MIN: u16 = 31;
+/// Largest FLL multiplier accepted by `ClockConfig::mclk_dcoclk_freq()` with a 32768 Hz FLL
+/// reference, giving just under the 24 MHz maximum
+pub const DCO_MULTIPLIER_MAX: u16 = 732;
+
+const DCO_FREQ_MIN: u32 = 1_000_000;
+const DCO_FREQ_MAX: u32 = 24_000_000;
+
+/// Frequency that the DCO locks to when `ClockConfig::mclk_dcoclk_freq()` is asked for
+/// `target_freq` Hz with REFOCLK as the FLL reference. This is the multiple of REFOCLK closest to
+/// the target, within the range of `DCO_MULTIPLIER_MIN` to `DCO_MULTIPLIER_MAX`.
+#[inline]
+pub const fn dco_freq(target_freq: u32) -> u32 {
+    dco_multiplier(target_freq, REFOCLK as u32) as u32 * REFOCLK as u32
+}
+
+// Multiple of the FLL reference closest to the target, kept within the DCO's range
+#[inline(always)]
+const fn dco_multiplier(target_freq: u32, fll_ref_freq: u32) -> u16 {
+    let min = DCO_FREQ_MIN.div_ceil(fll_ref_freq);
+    let max = DCO_FREQ_MAX / fll_ref_freq;
+    let multiplier = (target_freq + fll_ref_freq / 2) / fll_ref_freq;
+    let multiplier = if multiplier < min {
+        min
+    } else if multiplier > max {
+        max
+    } else {
+        multiplier
+    };
+    // FLLN is 10 bits
+    if multiplier > 1024 {
+        1024
+    } else {
+        multiplier as u16
+    }
+}
+
+// Smallest DCO range that covers `freq`, as chosen by TI's driverlib
+#[inline(always)]
+fn dcorsel_for(freq: u32) -> DCORSEL_A {
+    match freq {
+        0..=1_000_000 => DCORSEL_A::DCORSEL_0,
+        1_000_001..=2_000_000 => DCORSEL_A::DCORSEL_1,
+        2_000_001..=4_000_000 => DCORSEL_A::DCORSEL_2,
+        4_000_001..=8_000_000 => DCORSEL_A::DCORSEL_3,
+        8_000_001..=12_000_000 => DCORSEL_A::DCORSEL_4,
+        12_000_001..=16_000_000 => DCORSEL_A::DCORSEL_5,
+        16_000_001..=20_000_000 => DCORSEL_A::DCORSEL_6,
+        _ => DCORSEL_A::DCORSEL_7,
+    }
+}
+
 #[derive(Clone, Copy)]
 enum AclkSel {
     Vloclk,
     Refoclk,
+    // XT1 frequency and divider
+    Xt1(u32, Xt1AclkDiv),
 }
 
 impl AclkSel {
@@ -60,6 +133,7 @@ impl AclkSel {
         match self {
             AclkSel::Vloclk => SELA_A::VLOCLK,
             AclkSel::Refoclk => SELA_A::REFOCLK,
+            AclkSel::Xt1(..) => SELA_A::XT1CLK,
         }
     }
 
@@ -68,10 +142,313 @@ impl AclkSel {
         match self {
             AclkSel::Vloclk => VLOCLK,
             AclkSel::Refoclk => REFOCLK,
+            // Dividers are raised to keep this within 40 kHz
+            AclkSel::Xt1(freq, div) => (freq / div.divisor()) as u16,
+        }
+    }
+}
+
+/// XT1 frequency range
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Xt1Mode {
+    /// Low-frequency mode, for 32768 Hz watch crystals
+    LowFrequency,
+    /// High-frequency mode, for crystals and external clocks from 1 to 24 MHz
+    HighFrequency,
+}
+
+/// XT1 crystal drive strength. Stronger drive starts the crystal faster and copes with larger
+/// load capacitance, at the cost of more current.
+#[derive(Clone, Copy)]
+pub enum Xt1Drive {
+    /// Lowest current
+    Lowest,
+    /// Low current
+    Low,
+    /// High current
+    High,
+    /// Highest current, used out of reset
+    Highest,
+}
+
+impl Xt1Drive {
+    #[inline(always)]
+    fn xt1drive(self) -> XT1DRIVE_A {
+        match self {
+            Xt1Drive::Lowest => XT1DRIVE_A::XT1DRIVE_0,
+            Xt1Drive::Low => XT1DRIVE_A::XT1DRIVE_1,
+            Xt1Drive::High => XT1DRIVE_A::XT1DRIVE_2,
+            Xt1Drive::Highest => XT1DRIVE_A::XT1DRIVE_3,
         }
     }
 }
 
+/// Divider applied to XT1 when it sources ACLK. ACLK must not exceed 40 kHz, so a high-frequency
+/// XT1 needs to be divided down. `ClockConfig::aclk_xt1()` raises a divider that's too small.
+#[derive(Clone, Copy)]
+pub enum Xt1AclkDiv {
+    /// Divide by 1
+    _1,
+    /// Divide by 16
+    _16,
+    /// Divide by 32
+    _32,
+    /// Divide by 64
+    _64,
+    /// Divide by 128
+    _128,
+    /// Divide by 256
+    _256,
+    /// Divide by 384
+    _384,
+    /// Divide by 512
+    _512,
+    /// Divide by 768
+    _768,
+    /// Divide by 1024
+    _1024,
+}
+
+impl Xt1AclkDiv {
+    // Smallest divider that brings `freq` down to the 40 kHz ACLK limit
+    #[inline(always)]
+    const fn min_for(freq: u32) -> Self {
+        const LIMIT: u32 = 40_000;
+        if freq <= LIMIT {
+            Xt1AclkDiv::_1
+        } else if freq <= LIMIT * 16 {
+            Xt1AclkDiv::_16
+        } else if freq <= LIMIT * 32 {
+            Xt1AclkDiv::_32
+        } else if freq <= LIMIT * 64 {
+            Xt1AclkDiv::_64
+        } else if freq <= LIMIT * 128 {
+            Xt1AclkDiv::_128
+        } else if freq <= LIMIT * 256 {
+            Xt1AclkDiv::_256
+        } else if freq <= LIMIT * 384 {
+            Xt1AclkDiv::_384
+        } else if freq <= LIMIT * 512 {
+            Xt1AclkDiv::_512
+        } else if freq <= LIMIT * 768 {
+            Xt1AclkDiv::_768
+        } else {
+            Xt1AclkDiv::_1024
+        }
+    }
+
+    #[inline(always)]
+    fn diva(self) -> DIVA_A {
+        match self {
+            Xt1AclkDiv::_1 => DIVA_A::_1,
+            Xt1AclkDiv::_16 => DIVA_A::_16,
+            Xt1AclkDiv::_32 => DIVA_A::_32,
+            Xt1AclkDiv::_64 => DIVA_A::_64,
+            Xt1AclkDiv::_128 => DIVA_A::_128,
+            Xt1AclkDiv::_256 => DIVA_A::_256,
+            Xt1AclkDiv::_384 => DIVA_A::_384,
+            Xt1AclkDiv::_512 => DIVA_A::_512,
+            Xt1AclkDiv::_768 => DIVA_A::_768,
+            Xt1AclkDiv::_1024 => DIVA_A::_1024,
+        }
+    }
+
+    #[inline(always)]
+    const fn divisor(self) -> u32 {
+        match self {
+            Xt1AclkDiv::_1 => 1,
+            Xt1AclkDiv::_16 => 16,
+            Xt1AclkDiv::_32 => 32,
+            Xt1AclkDiv::_64 => 64,
+            Xt1AclkDiv::_128 => 128,
+            Xt1AclkDiv::_256 => 256,
+            Xt1AclkDiv::_384 => 384,
+            Xt1AclkDiv::_512 => 512,
+            Xt1AclkDiv::_768 => 768,
+            Xt1AclkDiv::_1024 => 1024,
+        }
+    }
+}
+
+// The largest dividers keep ACLK within 40 kHz across XT1's whole range
+const _: () = {
+    assert!(24_000_000 / Xt1AclkDiv::min_for(24_000_000).divisor() <= 40_000);
+    assert!(Xt1AclkDiv::min_for(32768).divisor() == 1);
+    assert!(Xt1AclkDiv::min_for(4_000_000).divisor() == 128);
+    assert!(Xt1AclkDiv::min_for(20_480_001).divisor() == 768);
+};
+
+/// XIN pin for XT1
+pub struct Xt1InPin;
+impl<DIR> From<Pin<P2, Pin7, Alternate2<DIR>>> for Xt1InPin {
+    #[inline(always)]
+    fn from(_val: Pin<P2, Pin7, Alternate2<DIR>>) -> Self {
+        Xt1InPin
+    }
+}
+
+/// XOUT pin for XT1
+pub struct Xt1OutPin;
+impl<DIR> From<Pin<P2, Pin6, Alternate2<DIR>>> for Xt1OutPin {
+    #[inline(always)]
+    fn from(_val: Pin<P2, Pin6, Alternate2<DIR>>) -> Self {
+        Xt1OutPin
+    }
+}
+
+// XT1 settings copied into `ClockConfig`
+#[derive(Clone, Copy)]
+struct Xt1Settings {
+    freq: u32,
+    mode: Xt1Mode,
+    drive: Xt1Drive,
+    bypass: bool,
+    startup_timeout_ms: u16,
+}
+
+/// XT1 oscillator, running from a crystal between XIN (P2.7) and XOUT (P2.6) or from an external
+/// clock on XIN
+///
+/// Pass a reference to `ClockConfig` to use XT1 as a clock source. XT1 is started when the clock
+/// configuration is frozen and then left running.
+pub struct Xt1 {
+    settings: Xt1Settings,
+    _xin: Xt1InPin,
+    _xout: Option<Xt1OutPin>,
+}
+
+impl Xt1 {
+    /// XT1 driving a crystal of frequency `freq` Hz
+    #[inline]
+    pub fn crystal<I: Into<Xt1InPin>, O: Into<Xt1OutPin>>(
+        xin: I,
+        xout: O,
+        mode: Xt1Mode,
+        freq: u32,
+    ) -> Self {
+        Xt1 {
+            settings: Xt1Settings {
+                freq,
+                mode,
+                drive: Xt1Drive::Highest,
+                bypass: false,
+                startup_timeout_ms: 2000,
+            },
+            _xin: xin.into(),
+            _xout: Some(xout.into()),
+        }
+    }
+
+    /// XT1 bypassed by an external square wave of frequency `freq` Hz on XIN. XOUT isn't used.
+    #[inline]
+    pub fn bypass<I: Into<Xt1InPin>>(xin: I, mode: Xt1Mode, freq: u32) -> Self {
+        Xt1 {
+            settings: Xt1Settings {
+                freq,
+                mode,
+                drive: Xt1Drive::Lowest,
+                bypass: true,
+                startup_timeout_ms: 2000,
+            },
+            _xin: xin.into(),
+            _xout: None,
+        }
+    }
+
+    /// Set the crystal drive strength. Defaults to `Xt1Drive::Highest`.
+    #[inline]
+    pub fn drive(mut self, drive: Xt1Drive) -> Self {
+        self.settings.drive = drive;
+        self
+    }
+
+    /// How long to wait for XT1 to start without faults when the clock configuration is frozen.
+    /// Defaults to 2 seconds, which covers most 32768 Hz crystals. The wait is timed in cycles of
+    /// MCLK at its reset frequency of about 1 MHz, so it's accurate to the DCO's factory trim.
+    #[inline]
+    pub fn startup_timeout_ms(mut self, ms: u16) -> Self {
+        self.settings.startup_timeout_ms = ms;
+        self
+    }
+
+    /// XT1 frequency
+    #[inline]
+    pub fn freq(&self) -> u32 {
+        self.settings.freq
+    }
+
+    /// Whether XT1 has a fault, either because it failed to start before the timeout or because it
+    /// stopped later. While it does, clocks sourced from XT1 run from REFOCLK instead. See
+    /// `ClockConfig::fll_ref_xt1()` for the effect on the DCO.
+    #[inline]
+    pub fn is_faulted(&self) -> bool {
+        let cs = unsafe { &*pac::CS::ptr() };
+        cs.csctl7.read().xt1offg().bit_is_set()
+    }
+
+    /// Try to clear the XT1 fault, returning whether it stayed cleared. Clocks sourced from XT1
+    /// switch back to it once the fault is cleared.
+    #[inline]
+    pub fn clear_fault(&mut self) -> bool {
+        clear_xt1_fault()
+    }
+
+    /// Enable the oscillator fault interrupt, which fires the NMI vector when XT1 faults
+    #[inline]
+    pub fn enable_fault_interrupt(&mut self) {
+        let sfr = unsafe { &*pac::SFR::ptr() };
+        unsafe { sfr.sfrie1.set_bits(|w| w.ofie().set_bit()) };
+    }
+
+    /// Disable the oscillator fault interrupt
+    #[inline]
+    pub fn disable_fault_interrupt(&mut self) {
+        let sfr = unsafe { &*pac::SFR::ptr() };
+        unsafe { sfr.sfrie1.clear_bits(|w| w.ofie().clear_bit()) };
+    }
+}
+
+// Clear the XT1 and oscillator fault flags, returning whether XT1 is running. The flags are set
+// again immediately by the hardware while the fault persists.
+#[inline]
+fn clear_xt1_fault() -> bool {
+    let cs = unsafe { &*pac::CS::ptr() };
+    let sfr = unsafe { &*pac::SFR::ptr() };
+    unsafe { cs.csctl7.clear_bits(|w| w.xt1offg().clear_bit()) };
+    unsafe { sfr.sfrifg1.clear_bits(|w| w.ofifg().clear_bit()) };
+    cs.csctl7.read().xt1offg().bit_is_clear()
+}
+
+// Smallest FLL reference divider that brings `freq` down to the 40 kHz limit of the FLL
+// reference. The largest divider covers XT1's whole range of up to 24 MHz.
+#[inline(always)]
+const fn fllrefdiv_for(freq: u32) -> (FLLREFDIV_A, u32) {
+    const LIMIT: u32 = 40_000;
+    if freq <= LIMIT {
+        (FLLREFDIV_A::_1, 1)
+    } else if freq <= LIMIT * 32 {
+        (FLLREFDIV_A::_32, 32)
+    } else if freq <= LIMIT * 64 {
+        (FLLREFDIV_A::_64, 64)
+    } else if freq <= LIMIT * 128 {
+        (FLLREFDIV_A::_128, 128)
+    } else if freq <= LIMIT * 256 {
+        (FLLREFDIV_A::_256, 256)
+    } else if freq <= LIMIT * 512 {
+        (FLLREFDIV_A::_512, 512)
+    } else if freq <= LIMIT * 640 {
+        (FLLREFDIV_A::FLLREFDIV_6, 640)
+    } else {
+        (FLLREFDIV_A::FLLREFDIV_7, 768)
+    }
+}
+
+const _: () = {
+    assert!(fllrefdiv_for(32768).1 == 1);
+    assert!(fllrefdiv_for(8_000_000).1 == 256);
+    assert!(24_000_000 / fllrefdiv_for(24_000_000).1 <= 40_000);
+};
+
 /// Selectable DCOCLK frequencies when using factory trim settings.
 /// Actual frequencies may be slightly higher.
 #[derive(Clone, Copy)]
@@ -170,6 +547,8 @@ pub struct ClockConfig<MCLK, SMCLK> {
     mclk_div: MclkDiv,
     aclk_sel: AclkSel,
     smclk: SMCLK,
+    xt1: Option<Xt1Settings>,
+    fll_ref_xt1: bool,
 }
 
 macro_rules! make_clkconf {
@@ -180,6 +559,8 @@ macro_rules! make_clkconf {
             mclk_div: $conf.mclk_div,
             aclk_sel: $conf.aclk_sel,
             smclk: $smclk,
+            xt1: $conf.xt1,
+            fll_ref_xt1: $conf.fll_ref_xt1,
         }
     };
 }
@@ -193,6 +574,8 @@ impl ClockConfig<NoClockDefined, NoClockDefined> {
             mclk: NoClockDefined,
             mclk_div: MclkDiv::_1,
             aclk_sel: AclkSel::Refoclk,
+            xt1: None,
+            fll_ref_xt1: false,
         }
     }
 }
@@ -212,6 +595,38 @@ impl<MCLK, SMCLK> ClockConfig<MCLK, SMCLK> {
         self
     }
 
+    /// Select XT1 for ACLK, divided by `div`. Frequency is `xt1.freq() / div` Hz. ACLK must not
+    /// exceed 40 kHz, so if `div` is too small for that, the smallest divider that's large enough
+    /// is used instead.
+    #[inline]
+    pub fn aclk_xt1(mut self, xt1: &Xt1, div: Xt1AclkDiv) -> Self {
+        let freq = xt1.settings.freq;
+        let min_div = Xt1AclkDiv::min_for(freq);
+        let div = if div.divisor() < min_div.divisor() {
+            min_div
+        } else {
+            div
+        };
+        self.aclk_sel = AclkSel::Xt1(freq, div);
+        self.xt1 = Some(xt1.settings);
+        self
+    }
+
+    /// Use XT1 instead of REFOCLK as the FLL reference, so the DCO is as accurate as the crystal.
+    /// DCO frequencies are then multiples of the XT1 frequency. A high-frequency XT1 is divided
+    /// down to at most 40 kHz first.
+    ///
+    /// If XT1 hasn't started by the end of its startup timeout, the FLL is referenced to REFOCLK
+    /// instead. If XT1 faults later, the hardware switches the FLL reference to REFOCLK, but keeps
+    /// the divider chosen for XT1. With a high-frequency XT1, the DCO then runs far below its
+    /// target (by a factor of up to 768) until the fault is cleared.
+    #[inline]
+    pub fn fll_ref_xt1(mut self, xt1: &Xt1) -> Self {
+        self.fll_ref_xt1 = true;
+        self.xt1 = Some(xt1.settings);
+        self
+    }
+
     /// Select REFOCLK for MCLK and set the MCLK divider. Frequency is `10000 / mclk_div` Hz.
     #[inline]
     pub fn mclk_refoclk(self, mclk_div: MclkDiv) -> ClockConfig<MclkDefined, SMCLK> {
@@ -245,6 +660,43 @@ impl<MCLK, SMCLK> ClockConfig<MCLK, SMCLK> {
         }
     }
 
+    /// Select XT1 for MCLK and set the MCLK divider. Frequency is `xt1.freq() / mclk_div` Hz.
+    #[inline]
+    pub fn mclk_xt1(mut self, xt1: &Xt1, mclk_div: MclkDiv) -> ClockConfig<MclkDefined, SMCLK> {
+        self.xt1 = Some(xt1.settings);
+        ClockConfig {
+            mclk_div,
+            ..make_clkconf!(
+                self,
+                MclkDefined(MclkSel::Xt1(xt1.settings.freq)),
+                self.smclk
+            )
+        }
+    }
+
+    /// Select DCOCLK for MCLK with FLL for stabilization, locked to the multiple of the FLL
+    /// reference closest to `target_freq` Hz. With REFOCLK as the reference, frequency is
+    /// `dco_freq(target_freq) / mclk_div` Hz, which is also the frequency that the returned clock
+    /// objects use. Targets outside of about 1 to 24 MHz are clamped to that range.
+    ///
+    /// Unlike `mclk_dcoclk()`, the DCO frequency trim is calibrated in software when the
+    /// configuration is frozen, so that the FLL locks in the middle of the DCO's tuning range.
+    #[inline]
+    pub fn mclk_dcoclk_freq(
+        self,
+        target_freq: u32,
+        mclk_div: MclkDiv,
+    ) -> ClockConfig<MclkDefined, SMCLK> {
+        ClockConfig {
+            mclk_div,
+            ..make_clkconf!(
+                self,
+                MclkDefined(MclkSel::DcoclkTrimmed(target_freq)),
+                self.smclk
+            )
+        }
+    }
+
     /// Enable SMCLK and set SMCLK divider, which divides the MCLK frequency
     #[inline]
     pub fn smclk_on(self, div: SmclkDiv) -> ClockConfig<MCLK, SmclkDefined> {
@@ -271,38 +723,128 @@ fn fll_on() {
 }
 
 impl<SMCLK: SmclkState> ClockConfig<MclkDefined, SMCLK> {
+    // FLL reference selection, divider and the divided reference frequency
+    #[inline(always)]
+    fn fll_ref(&self) -> (SELREF_A, FLLREFDIV_A, u32) {
+        match self.xt1 {
+            Some(xt1) if self.fll_ref_xt1 => {
+                let (refdiv, divisor) = fllrefdiv_for(xt1.freq);
+                (SELREF_A::XT1CLK, refdiv, xt1.freq / divisor)
+            }
+            _ => (SELREF_A::REFOCLK, FLLREFDIV_A::_1, REFOCLK as u32),
+        }
+    }
+
+    // Undivided MCLK frequency
+    #[inline(always)]
+    fn mclk_source_freq(&self) -> u32 {
+        self.mclk.0.freq(self.fll_ref().2)
+    }
+
+    // Start XT1 and wait for it to run without faults, up to the startup timeout. Returns whether
+    // it started. If it times out, clocks sourced from XT1 fall back to REFOCLK and the fault
+    // stays visible in XT1OFFG.
+    #[inline]
+    fn configure_xt1(&self) -> bool {
+        let xt1 = match self.xt1 {
+            Some(xt1) => xt1,
+            None => return false,
+        };
+        let diva = match self.aclk_sel {
+            AclkSel::Xt1(_, div) => div.diva(),
+            _ => DIVA_A::_1,
+        };
+        let hffreq = if xt1.freq <= 6_000_000 {
+            XT1HFFREQ_A::XT1HFFREQ_0
+        } else if xt1.freq <= 16_000_000 {
+            XT1HFFREQ_A::XT1HFFREQ_1
+        } else {
+            XT1HFFREQ_A::XT1HFFREQ_2
+        };
+        self.periph.csctl6.write(|w| {
+            w.xt1autooff()
+                .clear_bit()
+                .xt1agcoff()
+                .clear_bit()
+                .xts()
+                .bit(xt1.mode == Xt1Mode::HighFrequency)
+                .xt1bypass()
+                .bit(xt1.bypass)
+                .xt1drive()
+                .variant(xt1.drive.xt1drive())
+                .xt1hffreq()
+                .variant(hffreq)
+                .diva()
+                .variant(diva)
+                .xt1faultoff()
+                .clear_bit()
+        });
+
+        // MCLK is still at its reset frequency
+        let cycles_per_ms = DcoclkFreqSel::_1MHz.freq().div_ceil(1000);
+        for _ in 0..xt1.startup_timeout_ms {
+            unsafe { self.periph.csctl7.clear_bits(|w| w.dcoffg().clear_bit()) };
+            if clear_xt1_fault() {
+                return true;
+            }
+            delay_cycles(cycles_per_ms);
+        }
+        clear_xt1_fault()
+    }
+
     #[inline]
     fn configure_dco_fll(&self) {
-        // Run FLL configuration procedure from the user's guide if we are using DCO
-        if let MclkSel::Dcoclk(target_freq) = self.mclk.0 {
-            fll_off();
-            msp430::asm::nop();
-            msp430::asm::nop();
-            msp430::asm::nop();
-            msp430::asm::nop();
-            msp430::asm::nop();
-            msp430::asm::nop();
-
-            self.periph.csctl3.write(|w| w.selref().refoclk());
-            self.periph.csctl0.write(|w| unsafe { w.bits(0) });
-            self.periph
-                .csctl1
-                .write(|w| w.dcorsel().variant(target_freq.dcorsel()));
-            self.periph.csctl2.write(|w| {
-                unsafe { w.flln().bits(target_freq.multiplier() - 1) }
-                    .flld()
-                    ._1()
-            });
-
-            msp430::asm::nop();
-            msp430::asm::nop();
-            msp430::asm::nop();
-            msp430::asm::nop();
-            msp430::asm::nop();
-            msp430::asm::nop();
-            msp430::asm::nop();
-            fll_on();
+        let (selref, refdiv, ref_freq) = self.fll_ref();
+        let (dcorsel, multiplier, trim) = match self.mclk.0 {
+            MclkSel::Dcoclk(target_freq) => {
+                (target_freq.dcorsel(), target_freq.multiplier(), false)
+            }
+            MclkSel::DcoclkTrimmed(target_freq) => (
+                dcorsel_for(self.mclk_source_freq()),
+                dco_multiplier(target_freq, ref_freq),
+                true,
+            ),
+            MclkSel::Refoclk | MclkSel::Vloclk | MclkSel::Xt1(_) => return,
+        };
 
+        // Run FLL configuration procedure from the user's guide
+        fll_off();
+        msp430::asm::nop();
+        msp430::asm::nop();
+        msp430::asm::nop();
+        msp430::asm::nop();
+        msp430::asm::nop();
+        msp430::asm::nop();
+
+        self.periph
+            .csctl3
+            .write(|w| w.selref().variant(selref).fllrefdiv().variant(refdiv));
+        self.periph.csctl0.write(|w| unsafe { w.bits(0) });
+        self.periph.csctl1.write(|w| {
+            let w = w.dcorsel().variant(dcorsel);
+            if trim {
+                // Start the trim search in the middle of the DCOFTRIM range
+                unsafe { w.dcoftrimen().set_bit().dcoftrim().bits(3) }
+            } else {
+                w
+            }
+        });
+        self.periph
+            .csctl2
+            .write(|w| unsafe { w.flln().bits(multiplier - 1) }.flld()._1());
+
+        msp430::asm::nop();
+        msp430::asm::nop();
+        msp430::asm::nop();
+        msp430::asm::nop();
+        msp430::asm::nop();
+        msp430::asm::nop();
+        msp430::asm::nop();
+        fll_on();
+
+        if trim {
+            software_trim(&self.periph, self.mclk_source_freq());
+        } else {
             while !self.periph.csctl7.read().fllunlock().is_fllunlock_0() {}
         }
     }
@@ -338,15 +880,98 @@ impl<SMCLK: SmclkState> ClockConfig<MclkDefined, SMCLK> {
     }
 }
 
+// Busy-wait for at least `cycles` MCLK cycles, like `__delay_cycles()`. Each iteration of the
+// loop is a DEC (1 cycle) and a JNZ (2 cycles).
+#[inline(always)]
+fn delay_cycles(cycles: u32) {
+    let mut iters = cycles.div_ceil(3);
+    while iters > 0 {
+        let n = iters.min(u16::MAX as u32) as u16;
+        unsafe { asm!("2:", "dec.w {0}", "jnz 2b", inout(reg) n => _, options(nomem, nostack)) };
+        iters -= n as u32;
+    }
+}
+
+// Center of the DCO tap range. The FLL has the most room to track temperature and voltage changes
+// when it locks near this tap.
+const DCO_MID_TAP: u16 = 256;
+
+// DCOFTRIM software trim from TI's MSP430FR2xx reference code. With the FLL running, step
+// DCOFTRIM until the DCO tap that the FLL locks to crosses the middle of its range, then keep the
+// setting whose tap was closest to the middle. MCLK runs from the DCO while this runs, so
+// `dco_freq` is used to time the waits.
+fn software_trim(cs: &pac::CS, dco_freq: u32) {
+    let mut old_tap: Option<u16> = None;
+    // Delta from the middle tap, CSCTL0 and CSCTL1 of the best setting so far
+    let mut best = (u16::MAX, 0, 0);
+
+    loop {
+        cs.csctl0.write(|w| unsafe { w.bits(DCO_MID_TAP) });
+        loop {
+            unsafe { cs.csctl7.clear_bits(|w| w.dcoffg().clear_bit()) };
+            if cs.csctl7.read().dcoffg().bit_is_clear() {
+                break;
+            }
+        }
+        // Give the lock status 24 cycles of the FLL reference clock to become stable. Like TI's
+        // reference code this waits 3000 MCLK cycles per MHz, which is 3 ms.
+        delay_cycles(3 * dco_freq.div_ceil(1000));
+        loop {
+            let csctl7 = cs.csctl7.read();
+            if csctl7.fllunlock().is_fllunlock_0() || csctl7.dcoffg().bit_is_set() {
+                break;
+            }
+        }
+
+        let csctl0 = cs.csctl0.read().bits();
+        let csctl1 = cs.csctl1.read().bits();
+        let tap = csctl0 & 0x1FF;
+        let ftrim = ((csctl1 >> 4) & 0x7) as u8;
+
+        let (delta, crossed, next_ftrim) = if tap < DCO_MID_TAP {
+            let crossed = matches!(old_tap, Some(old) if old >= DCO_MID_TAP);
+            (DCO_MID_TAP - tap, crossed, ftrim.checked_sub(1))
+        } else {
+            let crossed = matches!(old_tap, Some(old) if old < DCO_MID_TAP);
+            (
+                tap - DCO_MID_TAP,
+                crossed,
+                Some(ftrim + 1).filter(|&t| t <= 7),
+            )
+        };
+        if delta < best.0 {
+            best = (delta, csctl0, csctl1);
+        }
+
+        // Stop once the tap crosses the middle or DCOFTRIM runs out of range
+        match next_ftrim {
+            Some(ftrim) if !crossed => cs.csctl1.modify(|_, w| unsafe { w.dcoftrim().bits(ftrim) }),
+            _ => break,
+        }
+        old_tap = Some(tap);
+    }
+
+    cs.csctl0.write(|w| unsafe { w.bits(best.1) });
+    cs.csctl1.write(|w| unsafe { w.bits(best.2) });
+    while !cs.csctl7.read().fllunlock().is_fllunlock_0() {}
+}
+
 impl ClockConfig<MclkDefined, SmclkDefined> {
     /// Apply clock configuration to hardware and return SMCLK and ACLK clock objects.
     /// Also returns delay provider
     #[inline]
-    pub fn freeze(self, fram: &mut Fram) -> (Smclk, Aclk, Delay) {
-        let mclk_freq = self.mclk.0.freq() >> (self.mclk_div as u32);
-        unsafe { Self::configure_fram(fram, mclk_freq) };
+    pub fn freeze(mut self, fram: &mut Fram) -> (Smclk, Aclk, Delay) {
+        // If XT1 didn't start, lock the FLL to undivided REFOCLK so the DCO still hits its target
+        if !self.configure_xt1() {
+            self.fll_ref_xt1 = false;
+        }
+        let mclk_freq = self.mclk_source_freq() >> (self.mclk_div as u32);
+        // MCLK runs from the undivided DCO until the dividers are set, which takes a while when
+        // the DCO is being trimmed, so the wait states need to cover that first
+        unsafe { Self::configure_fram(fram, self.mclk_source_freq()) };
         self.configure_dco_fll();
         self.configure_cs();
+        unsafe { Self::configure_fram(fram, mclk_freq) };
         (
             Smclk(mclk_freq >> (self.smclk.0 as u32)),
             Aclk(self.aclk_sel.freq()),
@@ -359,11 +984,18 @@ impl ClockConfig<MclkDefined, SmclkDisabled> {
     /// Apply clock configuration to hardware and return ACLK clock object, as SMCLK is disabled.
     /// Also returns delay provider.
     #[inline]
-    pub fn freeze(self, fram: &mut Fram) -> (Aclk, Delay) {
-        let mclk_freq = self.mclk.0.freq() >> (self.mclk_div as u32);
+    pub fn freeze(mut self, fram: &mut Fram) -> (Aclk, Delay) {
+        // If XT1 didn't start, lock the FLL to undivided REFOCLK so the DCO still hits its target
+        if !self.configure_xt1() {
+            self.fll_ref_xt1 = false;
+        }
+        let mclk_freq = self.mclk_source_freq() >> (self.mclk_div as u32);
+        // MCLK runs from the undivided DCO until the dividers are set, which takes a while when
+        // the DCO is being trimmed, so the wait states need to cover that first
+        unsafe { Self::configure_fram(fram, self.mclk_source_freq()) };
         self.configure_dco_fll();
-        unsafe { Self::configure_fram(fram, mclk_freq) };
         self.configure_cs();
+        unsafe { Self::configure_fram(fram, mclk_freq) };
         (Aclk(self.aclk_sel.freq()), Delay::new(mclk_freq))
     }
 }
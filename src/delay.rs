@@ -1,5 +1,6 @@
 //! Embedded hal delay implementation
 use crate::hal::blocking::delay::DelayMs;
+use embedded_hal_1::delay::DelayNs;
 use msp430::asm;
 
 /// Delay provider struct
@@ -17,6 +18,13 @@ impl Delay {
             nops_per_ms: (nops as u16),
         }
     }
+
+    #[inline(always)]
+    fn nops(&self, count: u32) {
+        for _ in 0..count {
+            asm::nop();
+        }
+    }
 }
 
 impl DelayMs<u16> for Delay {
@@ -29,3 +37,25 @@ impl DelayMs<u16> for Delay {
         }
     }
 }
+
+impl DelayNs for Delay {
+    /// The delay resolution is one microsecond, so `ns` is rounded up to the nearest microsecond.
+    #[inline]
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay_us(ns.div_ceil(1000));
+    }
+
+    #[inline]
+    fn delay_us(&mut self, us: u32) {
+        DelayNs::delay_ms(self, us / 1000);
+        // Remainder is below 1000, so this can't overflow
+        self.nops(((us % 1000) * self.nops_per_ms as u32).div_ceil(1000));
+    }
+
+    #[inline]
+    fn delay_ms(&mut self, ms: u32) {
+        for _ in 0..ms {
+            self.nops(self.nops_per_ms as u32);
+        }
+    }
+}
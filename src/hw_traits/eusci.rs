@@ -56,6 +56,14 @@ pub enum Ucmode {
     I2CMode = 3,
 }
 
+#[derive(Copy, Clone)]
+pub enum UartUcmode {
+    Uart = 0,
+    IdleLineMultiproc = 1,
+    AddrBitMultiproc = 2,
+    AutoBaud = 3,
+}
+
 #[derive(Copy, Clone)]
 pub enum Ucglit {
     Max50ns = 0,
@@ -100,7 +108,18 @@ pub struct UcaCtlw0 {
     pub uc7bit: bool,
     pub ucspb: bool,
     pub ucssel: Ucssel,
+    pub ucmode: UartUcmode,
     pub ucrxeie: bool,
+    pub ucbrkie: bool,
+}
+
+pub struct UcaIrctl {
+    pub uciren: bool,
+    pub ucirtxclk: bool,
+    pub ucirtxpl: u8,
+    pub ucirrxfe: bool,
+    pub ucirrxpl: bool,
+    pub ucirrxfl: u8,
 }
 
 reg_struct! {
@@ -236,6 +255,36 @@ pub trait EUsciUart: Steal {
 
     fn statw_rd(&self) -> <Self as EUsciUart>::Statw;
 
+    fn ctl0_set_rst(&self);
+    fn ctl0_clear_rst(&self);
+
+    fn brw_rd(&self) -> u16;
+    // Returns (UCOS16, UCBRFx)
+    fn mctlw_rd(&self) -> (bool, u8);
+
+    // only call while in reset state
+    fn set_ucmode(&self, mode: UartUcmode);
+    fn ucmode_rd(&self) -> u8;
+
+    fn uctxbrk_set(&self);
+    fn uctxaddr_set(&self);
+    fn set_ucdorm(&self, dorm: bool);
+
+    // only call while in reset state
+    fn abctl_settings(&self, ucabden: bool);
+
+    // only call while in reset state
+    fn irctl_settings(&self, reg: UcaIrctl);
+
+    // only call while in reset state
+    fn ctl1_settings(&self, ucglit: u8);
+    fn ucbtoe_rd(&self) -> bool;
+    fn ucstoe_rd(&self) -> bool;
+    fn abctl_clear_timeouts(&self);
+
+    fn ie_rd(&self) -> u16;
+    fn ie_wr(&self, bits: u16);
+
     fn txie_set(&self);
     fn txie_clear(&self);
     fn rxie_set(&self);
@@ -314,11 +363,15 @@ pub trait EusciSPI: Steal {
 
     fn set_spi_mode(&self, mode: Mode);
 
+    fn set_ucmsb(&self, msb: bool);
+
     fn brw_wr(&self, val: u16);
 
     fn uclisten_set(&self);
     fn uclisten_clear(&self);
 
+    fn statw_rd(&self) -> Self::Statw;
+
     fn rxbuf_rd(&self) -> u8;
 
     fn txbuf_wr(&self, val: u8);
@@ -331,12 +384,22 @@ pub trait EusciSPI: Steal {
 
     fn clear_receive_interrupt(&self);
 
+    fn ie_rd(&self) -> u16;
+
+    fn ie_wr(&self, val: u16);
+
     fn transmit_flag(&self) -> bool;
 
     fn receive_flag(&self) -> bool;
 
     fn overrun_flag(&self) -> bool;
 
+    fn busy_flag(&self) -> bool;
+
+    fn framing_flag(&self) -> bool;
+
+    fn clear_framing_flag(&self);
+
     fn iv_rd(&self) -> u16;
 }
 
@@ -346,13 +409,14 @@ pub trait UartUcxStatw {
     fn ucpe(&self) -> bool;
     fn ucbrk(&self) -> bool;
     fn ucbusy(&self) -> bool;
+    fn ucaddr_ucidle(&self) -> bool;
 }
 
 pub trait SpiStatw {
     fn uclisten(&self) -> bool;
     fn ucfe(&self) -> bool;
     fn ucoe(&self) -> bool;
-    // fn ucbusy1(&self) -> bool;
+    fn ucbusy(&self) -> bool;
 }
 
 pub trait I2CUcbIfgOut {
@@ -415,6 +479,11 @@ macro_rules! eusci_impl {
                 unsafe { self.$ucxstatw().clear_bits(|w| w.uclisten().clear_bit()) }
             }
 
+            #[inline(always)]
+            fn statw_rd(&self) -> Self::Statw {
+                self.$ucxstatw().read()
+            }
+
             #[inline(always)]
             fn rxbuf_rd(&self) -> u8 {
                 self.$ucxrxbuf().read().ucrxbuf().bits()
@@ -445,6 +514,16 @@ macro_rules! eusci_impl {
                 unsafe { self.$ucxie().clear_bits(|w| w.ucrxie().clear_bit()) }
             }
 
+            #[inline(always)]
+            fn ie_rd(&self) -> u16 {
+                self.$ucxie().read().bits()
+            }
+
+            #[inline(always)]
+            fn ie_wr(&self, val: u16) {
+                self.$ucxie().write(|w| unsafe { w.bits(val) });
+            }
+
             #[inline(always)]
             // Set the SPI mode without disturbing the rest of the register.
             fn set_spi_mode(&self, mode: Mode) {
@@ -461,6 +540,11 @@ macro_rules! eusci_impl {
                     .ucckpl().bit(ucckpl));
             }
 
+            #[inline(always)]
+            fn set_ucmsb(&self, msb: bool) {
+                self.$ucxctlw0().modify(|_, w| w.ucmsb().bit(msb));
+            }
+
             #[inline(always)]
             fn transmit_flag(&self) -> bool {
                 self.$ucxifg().read().uctxifg().bit()
@@ -476,6 +560,21 @@ macro_rules! eusci_impl {
                 self.$ucxstatw().read().ucoe().bit()
             }
 
+            #[inline(always)]
+            fn busy_flag(&self) -> bool {
+                SpiStatw::ucbusy(&self.$ucxstatw().read())
+            }
+
+            #[inline(always)]
+            fn framing_flag(&self) -> bool {
+                self.$ucxstatw().read().ucfe().bit()
+            }
+
+            #[inline(always)]
+            fn clear_framing_flag(&self) {
+                unsafe { self.$ucxstatw().clear_bits(|w| w.ucfe().clear_bit()) }
+            }
+
             #[inline(always)]
             fn iv_rd(&self) -> u16 {
                 self.$ucxiv().read().uciv().bits()
@@ -498,10 +597,11 @@ macro_rules! eusci_impl {
                 self.ucoe().bit()
             }
 
-            // #[inline(always)]
-            // fn ucbusy1(&self) -> bool{
-            //     self.ucbusy().bit()
-            // }
+            // The PAC doesn't expose UCBUSY for the SPI status register, but it's always bit 0
+            #[inline(always)]
+            fn ucbusy(&self) -> bool {
+                self.bits() & 1 != 0
+            }
         }
     };
 }
@@ -509,7 +609,8 @@ macro_rules! eusci_impl {
 macro_rules! eusci_a_impl {
     ($intr_vec:ident,$EUsci:ident, $eusci:ident, $ucaxctlw0:ident, $ucaxctlw1:ident, $ucaxbrw:ident,
      $ucaxmctlw:ident, $ucaxstatw:ident, $ucaxrxbuf:ident, $ucaxtxbuf:ident, $ucaxie:ident,
-     $ucaxifg:ident, $ucaxiv:ident, $Statw:ty,
+     $ucaxifg:ident, $ucaxiv:ident, $ucaxabctl:ident,
+     $ucaxirctl:ident, $Statw:ty,
      $StatwSpi:ty,
      $ucaxctlw0spi:ident, $ucaxstatwspi:ident, $ucaxiespi:ident, $ucaxifgspi:ident) => {
         eusci_impl!(
@@ -546,11 +647,117 @@ macro_rules! eusci_a_impl {
                         .bit(reg.ucspb)
                         .ucssel()
                         .bits(reg.ucssel as u8)
+                        .ucmode()
+                        .bits(reg.ucmode as u8)
                         .ucrxeie()
                         .bit(reg.ucrxeie)
+                        .ucbrkie()
+                        .bit(reg.ucbrkie)
                 });
             }
 
+            #[inline(always)]
+            fn ctl0_set_rst(&self) {
+                unsafe { self.$ucaxctlw0().set_bits(|w| w.ucswrst().set_bit()) };
+            }
+
+            #[inline(always)]
+            fn ctl0_clear_rst(&self) {
+                unsafe { self.$ucaxctlw0().clear_bits(|w| w.ucswrst().clear_bit()) };
+            }
+
+            #[inline(always)]
+            fn brw_rd(&self) -> u16 {
+                self.$ucaxbrw().read().bits()
+            }
+
+            #[inline(always)]
+            fn mctlw_rd(&self) -> (bool, u8) {
+                let r = self.$ucaxmctlw.read();
+                (r.ucos16().bit(), r.ucbrf().bits())
+            }
+
+            #[inline(always)]
+            fn set_ucmode(&self, mode: UartUcmode) {
+                self.$ucaxctlw0().modify(|_, w| w.ucmode().bits(mode as u8));
+            }
+
+            #[inline(always)]
+            fn ucmode_rd(&self) -> u8 {
+                self.$ucaxctlw0().read().ucmode().bits()
+            }
+
+            #[inline(always)]
+            fn uctxbrk_set(&self) {
+                unsafe { self.$ucaxctlw0().set_bits(|w| w.uctxbrk().set_bit()) };
+            }
+
+            #[inline(always)]
+            fn uctxaddr_set(&self) {
+                unsafe { self.$ucaxctlw0().set_bits(|w| w.uctxaddr().set_bit()) };
+            }
+
+            #[inline(always)]
+            fn set_ucdorm(&self, dorm: bool) {
+                self.$ucaxctlw0().modify(|_, w| w.ucdorm().bit(dorm));
+            }
+
+            #[inline(always)]
+            fn abctl_settings(&self, ucabden: bool) {
+                self.$ucaxabctl.write(|w| w.ucabden().bit(ucabden));
+            }
+
+            #[inline(always)]
+            fn ctl1_settings(&self, ucglit: u8) {
+                self.$ucaxctlw1.write(|w| w.ucglit().bits(ucglit));
+            }
+
+            #[inline(always)]
+            fn irctl_settings(&self, reg: UcaIrctl) {
+                self.$ucaxirctl.write(|w| unsafe {
+                    w.uciren()
+                        .bit(reg.uciren)
+                        .ucirtxclk()
+                        .bit(reg.ucirtxclk)
+                        .ucirtxpl()
+                        .bits(reg.ucirtxpl)
+                        .ucirrxfe()
+                        .bit(reg.ucirrxfe)
+                        .ucirrxpl()
+                        .bit(reg.ucirrxpl)
+                        .ucirrxfl()
+                        .bits(reg.ucirrxfl)
+                });
+            }
+
+            #[inline(always)]
+            fn ucbtoe_rd(&self) -> bool {
+                self.$ucaxabctl.read().ucbtoe().bit()
+            }
+
+            #[inline(always)]
+            fn ucstoe_rd(&self) -> bool {
+                self.$ucaxabctl.read().ucstoe().bit()
+            }
+
+            #[inline(always)]
+            fn abctl_clear_timeouts(&self) {
+                unsafe {
+                    self.$ucaxabctl
+                        .clear_bits(|w| w.ucbtoe().clear_bit().ucstoe().clear_bit())
+                };
+            }
+
+            #[inline(always)]
+            fn ie_rd(&self) -> u16 {
+                self.$ucaxie().read().bits()
+            }
+
+            #[inline(always)]
+            fn ie_wr(&self, bits: u16) {
+                self.$ucaxie().write(|w| unsafe { w.bits(bits) });
+            }
+
             #[inline(always)]
             fn mctlw_settings(&self, ucos16: bool, ucbrs: u8, ucbrf: u8) {
                 self.$ucaxmctlw.write(|w| unsafe {
@@ -655,6 +862,11 @@ macro_rules! eusci_a_impl {
             fn ucbusy(&self) -> bool {
                 self.ucbusy().bit()
             }
+
+            #[inline(always)]
+            fn ucaddr_ucidle(&self) -> bool {
+                self.ucaddr_ucidle().bit()
+            }
         }
     };
 }
@@ -958,6 +1170,8 @@ eusci_a_impl!(
     uca0ie,
     uca0ifg,
     uca0iv,
+    uca0abctl,
+    uca0irctl,
     pac::e_usci_a0::uca0statw::R,
     pac::e_usci_a0::uca0statw_spi::R,
     uca0ctlw0_spi,
@@ -980,6 +1194,8 @@ eusci_a_impl!(
     uca1ie,
     uca1ifg,
     uca1iv,
+    uca1abctl,
+    uca1irctl,
     pac::e_usci_a1::uca1statw::R,
     pac::e_usci_a1::uca1statw_spi::R,
     uca1ctlw0_spi,
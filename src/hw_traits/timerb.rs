@@ -93,6 +93,9 @@ pub trait TimerB: Steal {
     fn tbie_clr(&self);
 
     fn tbxiv_rd(&self) -> u16;
+
+    /// Read the current timer count
+    fn tbr_rd(&self) -> u16;
 }
 
 pub trait CCRn<C>: Steal {
@@ -197,7 +200,7 @@ macro_rules! ccrn_impl {
 }
 
 macro_rules! timerb_impl {
-    ($TBx:ident, $tbx:ident, $tbxctl:ident, $tbxex:ident, $tbxiv:ident, $([$CCRn:ident, $tbxcctln:ident, $tbxccrn:ident]),*) => {
+    ($TBx:ident, $tbx:ident, $tbxctl:ident, $tbxex:ident, $tbxiv:ident, $tbxr:ident, $([$CCRn:ident, $tbxcctln:ident, $tbxccrn:ident]),*) => {
         impl Steal for pac::$TBx {
             #[inline(always)]
             unsafe fn steal() -> Self {
@@ -282,6 +285,18 @@ macro_rules! timerb_impl {
             fn tbxiv_rd(&self) -> u16 {
                 self.$tbxiv.read().bits()
             }
+
+            #[inline(always)]
+            fn tbr_rd(&self) -> u16 {
+                // The timer clock may be asynchronous to the CPU clock, so read until two
+                // consecutive reads agree
+                loop {
+                    let count = self.$tbxr.read().bits();
+                    if count == self.$tbxr.read().bits() {
+                        return count;
+                    }
+                }
+            }
         }
 
         $(ccrn_impl!($TBx, $CCRn, $tbxcctln, $tbxccrn);)*
@@ -294,6 +309,7 @@ timerb_impl!(
     tb0ctl,
     tb0ex0,
     tb0iv,
+    tb0r,
     [CCR0, tb0cctl0, tb0ccr0],
     [CCR1, tb0cctl1, tb0ccr1],
     [CCR2, tb0cctl2, tb0ccr2]
@@ -305,6 +321,7 @@ timerb_impl!(
     tb1ctl,
     tb1ex0,
     tb1iv,
+    tb1r,
     [CCR0, tb1cctl0, tb1ccr0],
     [CCR1, tb1cctl1, tb1ccr1],
     [CCR2, tb1cctl2, tb1ccr2]
@@ -316,6 +333,7 @@ timerb_impl!(
     tb2ctl,
     tb2ex0,
     tb2iv,
+    tb2r,
     [CCR0, tb2cctl0, tb2ccr0],
     [CCR1, tb2cctl1, tb2ccr1],
     [CCR2, tb2cctl2, tb2ccr2]
@@ -327,6 +345,7 @@ timerb_impl!(
     tb3ctl,
     tb3ex0,
     tb3iv,
+    tb3r,
     [CCR0, tb3cctl0, tb3ccr0],
     [CCR1, tb3cctl1, tb3ccr1],
     [CCR2, tb3cctl2, tb3ccr2],
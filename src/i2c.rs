@@ -8,6 +8,9 @@
 //!
 //! eUSCI_B1: {SCL:P4.7, SDA:P4.6}
 //!
+//! `I2cBus::release()` puts the eUSCI back into reset and returns the PAC peripheral and pins, so
+//! the eUSCI_B can be reconfigured, e.g. as an SPI bus, or left in reset to save power.
+//!
 
 use crate::clock::{Aclk, Smclk};
 use crate::gpio::{Pin1, Pin5};
@@ -328,14 +331,19 @@ impl<USCI: I2cUsci> I2CBusConfig<USCI, NoClockSet> {
 
 #[allow(private_bounds)]
 impl<USCI: I2cUsci> I2CBusConfig<USCI, ClockSet> {
-    /// Performs hardware configuration and creates the I2C bus
+    /// Performs hardware configuration and creates the I2C bus. The configuration is consumed, as
+    /// `I2cBus::release()` hands the PAC peripheral back.
     pub fn configure<C: Into<USCI::ClockPin>, D: Into<USCI::DataPin>>(
-        &self,
-        _scl: C,
-        _sda: D,
-    ) -> I2cBus<USCI> {
+        self,
+        scl: C,
+        sda: D,
+    ) -> I2cBus<USCI, C, D> {
         self.configure_regs();
-        I2cBus(PhantomData)
+        I2cBus {
+            scl,
+            sda,
+            _usci: PhantomData,
+        }
     }
 
     /// Performs hardware configuration
@@ -360,7 +368,23 @@ impl<USCI: I2cUsci> I2CBusConfig<USCI, ClockSet> {
 }
 
 /// I2C data bus
-pub struct I2cBus<USCI: I2cUsci>(PhantomData<USCI>);
+pub struct I2cBus<USCI: I2cUsci, SCL, SDA> {
+    scl: SCL,
+    sda: SDA,
+    _usci: PhantomData<USCI>,
+}
+
+impl<USCI: I2cUsci, SCL, SDA> I2cBus<USCI, SCL, SDA> {
+    /// Wait for any pending stop condition to be sent, put the eUSCI back into reset and return the
+    /// PAC peripheral along with the SCL and SDA pins, exactly as they were passed to `configure()`.
+    #[inline]
+    pub fn release(self) -> (USCI, SCL, SDA) {
+        let usci = unsafe { USCI::steal() };
+        while usci.uctxstp_rd() {}
+        usci.ctw0_set_rst();
+        (usci, self.scl, self.sda)
+    }
+}
 
 /// I2C transmit/receive errors
 #[derive(Clone, Copy, Debug)]
@@ -373,7 +397,7 @@ pub enum I2CErr {
     // Other errors such as the 'clock low timeout' UCCLTOIFG may appear here in future.
 }
 
-impl<USCI: I2cUsci> I2cBus<USCI> {
+impl<USCI: I2cUsci, SCL, SDA> I2cBus<USCI, SCL, SDA> {
     #[inline(always)]
     fn set_addressing_mode(&mut self, mode: AddressingMode) {
         let usci = unsafe { USCI::steal() };
@@ -484,7 +508,7 @@ impl<USCI: I2cUsci> I2cBus<USCI> {
     }
 }
 
-impl<USCI: I2cUsci> Read<SevenBitAddress> for I2cBus<USCI> {
+impl<USCI: I2cUsci, SCL, SDA> Read<SevenBitAddress> for I2cBus<USCI, SCL, SDA> {
     type Error = I2CErr;
     fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
         self.set_addressing_mode(AddressingMode::SevenBit);
@@ -493,7 +517,7 @@ impl<USCI: I2cUsci> Read<SevenBitAddress> for I2cBus<USCI> {
     }
 }
 
-impl<USCI: I2cUsci> Read<TenBitAddress> for I2cBus<USCI> {
+impl<USCI: I2cUsci, SCL, SDA> Read<TenBitAddress> for I2cBus<USCI, SCL, SDA> {
     type Error = I2CErr;
     fn read(&mut self, address: u16, buffer: &mut [u8]) -> Result<(), Self::Error> {
         self.set_addressing_mode(AddressingMode::TenBit);
@@ -502,7 +526,7 @@ impl<USCI: I2cUsci> Read<TenBitAddress> for I2cBus<USCI> {
     }
 }
 
-impl<USCI: I2cUsci> Write<SevenBitAddress> for I2cBus<USCI> {
+impl<USCI: I2cUsci, SCL, SDA> Write<SevenBitAddress> for I2cBus<USCI, SCL, SDA> {
     type Error = I2CErr;
     fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
         self.set_addressing_mode(AddressingMode::SevenBit);
@@ -511,7 +535,7 @@ impl<USCI: I2cUsci> Write<SevenBitAddress> for I2cBus<USCI> {
     }
 }
 
-impl<USCI: I2cUsci> Write<TenBitAddress> for I2cBus<USCI> {
+impl<USCI: I2cUsci, SCL, SDA> Write<TenBitAddress> for I2cBus<USCI, SCL, SDA> {
     type Error = I2CErr;
     fn write(&mut self, address: u16, bytes: &[u8]) -> Result<(), Self::Error> {
         self.set_addressing_mode(AddressingMode::TenBit);
@@ -520,7 +544,7 @@ impl<USCI: I2cUsci> Write<TenBitAddress> for I2cBus<USCI> {
     }
 }
 
-impl<USCI: I2cUsci> WriteRead<SevenBitAddress> for I2cBus<USCI> {
+impl<USCI: I2cUsci, SCL, SDA> WriteRead<SevenBitAddress> for I2cBus<USCI, SCL, SDA> {
     type Error = I2CErr;
     fn write_read(
         &mut self,
@@ -533,7 +557,7 @@ impl<USCI: I2cUsci> WriteRead<SevenBitAddress> for I2cBus<USCI> {
     }
 }
 
-impl<USCI: I2cUsci> WriteRead<TenBitAddress> for I2cBus<USCI> {
+impl<USCI: I2cUsci, SCL, SDA> WriteRead<TenBitAddress> for I2cBus<USCI, SCL, SDA> {
     type Error = I2CErr;
     fn write_read(
         &mut self,
@@ -34,9 +34,11 @@ pub mod capture;
 pub mod clock;
 pub mod fram;
 pub mod gpio;
+pub mod lin;
 pub mod pmm;
 pub mod prelude;
 pub mod pwm;
+pub mod rs485;
 pub mod rtc;
 pub mod serial;
 pub mod timer;
@@ -49,5 +51,14 @@ pub mod delay;
 pub mod i2c;
 pub mod spi;
 
+#[cfg(feature = "sdcard")]
+pub mod sdcard;
+#[cfg(feature = "spi-nor")]
+pub mod spi_nor;
+#[cfg(feature = "modbus")]
+pub mod modbus;
+#[cfg(any(feature = "log", feature = "defmt"))]
+pub mod logging;
+
 pub use embedded_hal as hal;
 pub use msp430fr2355 as pac;
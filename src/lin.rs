@@ -0,0 +1,305 @@
+//! LIN bus protocol
+//!
+//! Implements LIN 2.x frames on top of a serial UART connected to a LIN transceiver. The UART
+//! must be configured for 8 data bits, no parity, one stop bit and LSB first, with automatic
+//! baud rate detection enabled via `SerialConfig::auto_baud()`. Both masters and slaves rely on
+//! the transceiver echoing transmitted bytes back to Rx, which is used to detect bit errors.
+//!
+//! The protected ID and checksum helpers are plain functions that don't touch the hardware.
+
+mod frame;
+
+pub use frame::{checksum, frame_id, protected_id, ChecksumType};
+
+use crate::serial::{AutoBaudError, RecvError, Rx, SerialUsci, Tx};
+use embedded_hal::serial::{Read, Write};
+use nb::block;
+use void::ResultVoidExt;
+
+/// Maximum number of data bytes in a LIN frame
+pub const MAX_DATA_LEN: usize = 8;
+
+// Roughly how many times the Rx flag is polled while waiting for a byte before giving up. The
+// actual time depends on the CPU clock.
+const RESPONSE_POLL_LIMIT: u32 = 100_000;
+
+/// LIN errors
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum LinError {
+    /// A byte was received with an error
+    Recv(RecvError),
+    /// The break/sync field was invalid
+    Sync(AutoBaudError),
+    /// The protected ID had the wrong parity. Contains the received byte.
+    Parity(u8),
+    /// The checksum didn't match
+    Checksum,
+    /// A byte read back from the bus didn't match the one sent
+    BitError,
+    /// No response was received in time
+    Timeout,
+    /// More than 8 data bytes were given
+    InvalidLength,
+}
+
+impl From<RecvError> for LinError {
+    #[inline]
+    fn from(err: RecvError) -> Self {
+        LinError::Recv(err)
+    }
+}
+
+// Wait for a byte, giving up after a while
+fn read_byte<USCI: SerialUsci, RXPIN>(rx: &mut Rx<USCI, RXPIN>) -> Result<u8, LinError> {
+    for _ in 0..RESPONSE_POLL_LIMIT {
+        match rx.read() {
+            Ok(byte) => return Ok(byte),
+            Err(nb::Error::Other(err)) => return Err(err.into()),
+            Err(nb::Error::WouldBlock) => (),
+        }
+    }
+    Err(LinError::Timeout)
+}
+
+// Send a byte and check the transceiver echo
+fn write_byte<USCI: SerialUsci, TXPIN, RXPIN>(
+    tx: &mut Tx<USCI, TXPIN>,
+    rx: &mut Rx<USCI, RXPIN>,
+    byte: u8,
+) -> Result<(), LinError> {
+    block!(tx.write(byte)).void_unwrap();
+    if read_byte(rx)? == byte {
+        Ok(())
+    } else {
+        Err(LinError::BitError)
+    }
+}
+
+// Send data bytes followed by the checksum
+fn write_response<USCI: SerialUsci, TXPIN, RXPIN>(
+    tx: &mut Tx<USCI, TXPIN>,
+    rx: &mut Rx<USCI, RXPIN>,
+    kind: ChecksumType,
+    id: u8,
+    data: &[u8],
+) -> Result<(), LinError> {
+    for &byte in data {
+        write_byte(tx, rx, byte)?;
+    }
+    write_byte(tx, rx, checksum(kind, id, data))
+}
+
+// Receive data bytes and check the checksum
+fn read_response<USCI: SerialUsci, RXPIN>(
+    rx: &mut Rx<USCI, RXPIN>,
+    kind: ChecksumType,
+    id: u8,
+    buf: &mut [u8],
+) -> Result<(), LinError> {
+    for b in buf.iter_mut() {
+        *b = read_byte(rx)?;
+    }
+    if read_byte(rx)? == checksum(kind, id, buf) {
+        Ok(())
+    } else {
+        Err(LinError::Checksum)
+    }
+}
+
+/// LIN master node
+pub struct LinMaster<USCI: SerialUsci, TXPIN, RXPIN> {
+    tx: Tx<USCI, TXPIN>,
+    rx: Rx<USCI, RXPIN>,
+}
+
+impl<USCI: SerialUsci, TXPIN, RXPIN> LinMaster<USCI, TXPIN, RXPIN> {
+    /// Create a LIN master from a UART configured with automatic baud rate detection
+    #[inline]
+    pub fn new(tx: Tx<USCI, TXPIN>, rx: Rx<USCI, RXPIN>) -> Self {
+        LinMaster { tx, rx }
+    }
+
+    /// Return the Tx and Rx pins
+    #[inline]
+    pub fn free(self) -> (Tx<USCI, TXPIN>, Rx<USCI, RXPIN>) {
+        (self.tx, self.rx)
+    }
+
+    /// Send a frame header: break, sync and the protected ID for frame ID `id`
+    pub fn send_header(&mut self, id: u8) -> Result<(), LinError> {
+        block!(self.tx.write_break()).void_unwrap();
+        // The receiver sees our own break/sync field
+        for _ in 0..RESPONSE_POLL_LIMIT {
+            match self.rx.read_sync() {
+                Ok(_) => return write_byte(&mut self.tx, &mut self.rx, protected_id(id)),
+                Err(nb::Error::Other(err)) => return Err(LinError::Sync(err)),
+                Err(nb::Error::WouldBlock) => (),
+            }
+        }
+        Err(LinError::Timeout)
+    }
+
+    /// Send a header followed by a response published by the master
+    pub fn write_frame(
+        &mut self,
+        id: u8,
+        kind: ChecksumType,
+        data: &[u8],
+    ) -> Result<(), LinError> {
+        if data.len() > MAX_DATA_LEN {
+            return Err(LinError::InvalidLength);
+        }
+        self.send_header(id)?;
+        write_response(&mut self.tx, &mut self.rx, kind, id, data)
+    }
+
+    /// Send a header and receive `buf.len()` bytes of response published by a slave
+    pub fn read_frame(
+        &mut self,
+        id: u8,
+        kind: ChecksumType,
+        buf: &mut [u8],
+    ) -> Result<(), LinError> {
+        if buf.len() > MAX_DATA_LEN {
+            return Err(LinError::InvalidLength);
+        }
+        self.send_header(id)?;
+        read_response(&mut self.rx, kind, id, buf)
+    }
+}
+
+/// Whether a slave sends or receives the response to a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameDirection {
+    /// The slave sends the response
+    Publish,
+    /// The slave receives the response
+    Subscribe,
+}
+
+/// A frame handled by a `LinSlave`
+#[derive(Clone, Copy, Debug)]
+pub struct LinFrame {
+    id: u8,
+    direction: FrameDirection,
+    checksum: ChecksumType,
+    len: u8,
+    data: [u8; MAX_DATA_LEN],
+    updated: bool,
+}
+
+impl LinFrame {
+    /// A frame published by the slave. Up to 8 bytes of `data` are used.
+    #[inline]
+    pub fn publish(id: u8, checksum: ChecksumType, data: &[u8]) -> Self {
+        let mut frame = Self::subscribe(id, checksum, data.len());
+        frame.direction = FrameDirection::Publish;
+        frame.set_data(data);
+        frame
+    }
+
+    /// A frame with a `len` byte response that the slave receives. `len` is capped at 8.
+    #[inline]
+    pub fn subscribe(id: u8, checksum: ChecksumType, len: usize) -> Self {
+        LinFrame {
+            id: id & 0x3F,
+            direction: FrameDirection::Subscribe,
+            checksum,
+            len: len.min(MAX_DATA_LEN) as u8,
+            data: [0; MAX_DATA_LEN],
+            updated: false,
+        }
+    }
+
+    /// Frame ID
+    #[inline]
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Whether the slave publishes or subscribes to this frame
+    #[inline]
+    pub fn direction(&self) -> FrameDirection {
+        self.direction
+    }
+
+    /// Response data. For subscribed frames this is the most recently received response.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+
+    /// Update the response data. Extra bytes beyond the frame length are ignored.
+    #[inline]
+    pub fn set_data(&mut self, data: &[u8]) {
+        let len = data.len().min(self.len as usize);
+        self.data[..len].copy_from_slice(&data[..len]);
+    }
+
+    /// Whether a response has been sent or received since the last call, clearing the flag
+    #[inline]
+    pub fn take_updated(&mut self) -> bool {
+        core::mem::replace(&mut self.updated, false)
+    }
+}
+
+/// LIN slave node that responds to headers according to a table of frames
+pub struct LinSlave<'a, USCI: SerialUsci, TXPIN, RXPIN> {
+    tx: Tx<USCI, TXPIN>,
+    rx: Rx<USCI, RXPIN>,
+    frames: &'a mut [LinFrame],
+}
+
+impl<'a, USCI: SerialUsci, TXPIN, RXPIN> LinSlave<'a, USCI, TXPIN, RXPIN> {
+    /// Create a LIN slave from a UART configured with automatic baud rate detection. The slave
+    /// only responds to headers whose IDs are in `frames`.
+    #[inline]
+    pub fn new(tx: Tx<USCI, TXPIN>, rx: Rx<USCI, RXPIN>, frames: &'a mut [LinFrame]) -> Self {
+        LinSlave { tx, rx, frames }
+    }
+
+    /// Return the Tx and Rx pins and the frame table
+    #[inline]
+    pub fn free(self) -> (Tx<USCI, TXPIN>, Rx<USCI, RXPIN>, &'a mut [LinFrame]) {
+        (self.tx, self.rx, self.frames)
+    }
+
+    /// Frame table
+    #[inline]
+    pub fn frames(&mut self) -> &mut [LinFrame] {
+        self.frames
+    }
+
+    /// Wait for a header and handle the frame. Once a break/sync field is seen, this blocks until
+    /// the whole frame is done. Returns the frame ID if it was in the table, or `None` otherwise.
+    pub fn poll(&mut self) -> nb::Result<Option<u8>, LinError> {
+        self.rx
+            .read_sync()
+            .map_err(|err| err.map(LinError::Sync))?;
+
+        let pid = read_byte(&mut self.rx)?;
+        let id = frame_id(pid).ok_or(LinError::Parity(pid))?;
+
+        let Some(frame) = self.frames.iter_mut().find(|f| f.id == id) else {
+            return Ok(None);
+        };
+        let len = frame.len as usize;
+        match frame.direction {
+            FrameDirection::Publish => write_response(
+                &mut self.tx,
+                &mut self.rx,
+                frame.checksum,
+                id,
+                &frame.data[..len],
+            )?,
+            FrameDirection::Subscribe => {
+                let mut buf = [0; MAX_DATA_LEN];
+                read_response(&mut self.rx, frame.checksum, id, &mut buf[..len])?;
+                frame.data = buf;
+            }
+        }
+        frame.updated = true;
+        Ok(Some(id))
+    }
+}
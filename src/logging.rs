@@ -0,0 +1,171 @@
+//! Logging over a serial port
+//!
+//! Routes the output of the `log` crate (with the `log` feature) or `defmt` (with the `defmt`
+//! feature) to a configured `serial::Tx`. The logger consumes the `Tx`, so nothing else can write
+//! to that UART afterwards. It can't be handed back, so the UART and its pin stay with the logger
+//! for the rest of the program, even after switching the output to another Tx. Every log message is written inside a critical section, so
+//! messages from interrupts and the main thread never interleave. Writes block until the whole
+//! message is in the Tx buffer, which can hold off interrupts for a while at low baud rates.
+//!
+//! The critical section is provided by the `critical-section` crate, so an implementation must be
+//! linked in, e.g. by enabling the `critical-section-single-core` feature of the `msp430` crate.
+//!
+//! Messages logged before the logger is initialized are dropped.
+
+use crate::serial::{flush_blocking, write_all_blocking, SerialUsci, Tx};
+use core::cell::Cell;
+use critical_section::Mutex;
+
+// Type-erased handle to the Tx that owns the log output
+#[derive(Clone, Copy)]
+struct Sink {
+    write: fn(&[u8]),
+    flush: fn(),
+}
+
+impl Sink {
+    // Only the eUSCI type is kept. The Tx itself is consumed rather than stored, since the static
+    // can't hold a generic Tx, so the UART is given up for good.
+    #[inline]
+    fn new<USCI: SerialUsci, PIN>(_tx: Tx<USCI, PIN>) -> Self {
+        Sink {
+            write: write_all_blocking::<USCI>,
+            flush: flush_blocking::<USCI>,
+        }
+    }
+}
+
+static SINK: Mutex<Cell<Option<Sink>>> = Mutex::new(Cell::new(None));
+
+#[cfg(feature = "log")]
+mod log_impl {
+    use super::SINK;
+    use core::fmt::Write;
+
+    pub(super) struct SerialLogger;
+
+    pub(super) static LOGGER: SerialLogger = SerialLogger;
+
+    struct SinkWriter(fn(&[u8]));
+
+    impl Write for SinkWriter {
+        #[inline]
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            (self.0)(s.as_bytes());
+            Ok(())
+        }
+    }
+
+    impl log::Log for SerialLogger {
+        #[inline]
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::max_level()
+        }
+
+        fn log(&self, record: &log::Record) {
+            if !self.enabled(record.metadata()) {
+                return;
+            }
+            critical_section::with(|cs| {
+                if let Some(sink) = SINK.borrow(cs).get() {
+                    write!(
+                        SinkWriter(sink.write),
+                        "[{}] {}\r\n",
+                        record.level(),
+                        record.args()
+                    )
+                    .ok();
+                }
+            });
+        }
+
+        fn flush(&self) {
+            critical_section::with(|cs| {
+                if let Some(sink) = SINK.borrow(cs).get() {
+                    (sink.flush)();
+                }
+            });
+        }
+    }
+}
+
+/// Send the output of the `log` crate to `tx`, showing messages at `level` and above. Each
+/// message is written as `[LEVEL] message` followed by CRLF. Calling this again switches the
+/// output to the new Tx. `tx` is never returned, see the module docs.
+#[cfg(feature = "log")]
+pub fn init_log<USCI: SerialUsci, PIN>(tx: Tx<USCI, PIN>, level: log::LevelFilter) {
+    let sink = Sink::new(tx);
+    critical_section::with(|cs| {
+        SINK.borrow(cs).set(Some(sink));
+        // Racy only in that these can't be called concurrently, which the critical section
+        // prevents
+        unsafe {
+            log::set_logger_racy(&log_impl::LOGGER).ok();
+            log::set_max_level_racy(level);
+        }
+    });
+}
+
+#[cfg(feature = "defmt")]
+mod defmt_impl {
+    use super::{Sink, SINK};
+
+    // Only accessed between `acquire()` and `release()`, which are inside a critical section
+    static mut RESTORE_STATE: critical_section::RestoreState =
+        critical_section::RestoreState::invalid();
+    static mut ENCODER: defmt::Encoder = defmt::Encoder::new();
+    static mut SINK_COPY: Option<Sink> = None;
+    static mut TAKEN: bool = false;
+
+    #[defmt::global_logger]
+    struct DefmtLogger;
+
+    unsafe impl defmt::Logger for DefmtLogger {
+        fn acquire() {
+            let restore = unsafe { critical_section::acquire() };
+            // defmt forbids reentrant logging, e.g. from a `Format` impl that logs
+            if unsafe { TAKEN } {
+                panic!("defmt logger taken reentrantly");
+            }
+            unsafe {
+                TAKEN = true;
+                RESTORE_STATE = restore;
+                // We're in a critical section, so borrowing with a fresh token is fine
+                let cs = critical_section::CriticalSection::new();
+                SINK_COPY = SINK.borrow(cs).get();
+                if let Some(sink) = SINK_COPY {
+                    (*core::ptr::addr_of_mut!(ENCODER)).start_frame(sink.write);
+                }
+            }
+        }
+
+        unsafe fn flush() {
+            if let Some(sink) = SINK_COPY {
+                (sink.flush)();
+            }
+        }
+
+        unsafe fn release() {
+            if let Some(sink) = SINK_COPY {
+                (*core::ptr::addr_of_mut!(ENCODER)).end_frame(sink.write);
+            }
+            TAKEN = false;
+            critical_section::release(RESTORE_STATE);
+        }
+
+        unsafe fn write(bytes: &[u8]) {
+            if let Some(sink) = SINK_COPY {
+                (*core::ptr::addr_of_mut!(ENCODER)).write(bytes, sink.write);
+            }
+        }
+    }
+}
+
+/// Send `defmt` frames to `tx`. The frames are binary, so the other end must decode them with
+/// `defmt-print` or similar. Calling this again switches the output to the new Tx. `tx` is never
+/// returned, see the module docs.
+#[cfg(feature = "defmt")]
+pub fn init_defmt<USCI: SerialUsci, PIN>(tx: Tx<USCI, PIN>) {
+    let sink = Sink::new(tx);
+    critical_section::with(|cs| SINK.borrow(cs).set(Some(sink)));
+}
@@ -0,0 +1,362 @@
+//! Modbus RTU slave
+//!
+//! Requires the `modbus` feature. Serves requests from a Modbus RTU master against a
+//! user-supplied `ModbusMap` of coils, discrete inputs and registers. Frames are delimited by
+//! the 3.5 character line-idle gap by a `FrameReceiver`, normally `serial::RxTimeout`, and
+//! responses are sent through any `embedded_io::Write` transmitter, such as `serial::Tx` or
+//! `rs485::Rs485`.
+//!
+//! Supported function codes are 01 (read coils), 02 (read discrete inputs), 03 (read holding
+//! registers), 04 (read input registers), 05 (write single coil), 06 (write single register),
+//! 15 (write multiple coils) and 16 (write multiple registers). Anything else is answered with an
+//! illegal function exception. Requests to the broadcast address 0 are never answered, and are
+//! only executed if they are writes (function codes 05, 06, 15 and 16).
+//!
+//! `process_request()` contains the framing, CRC and dispatch logic without touching the hardware.
+
+use crate::serial::{char_times_to_ticks, RxEvent};
+
+/// Maximum size of a Modbus RTU frame in bytes
+pub const MAX_FRAME_LEN: usize = 256;
+
+const BROADCAST_ADDR: u8 = 0;
+
+/// Modbus exception codes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exception {
+    /// The function code isn't supported
+    IllegalFunction = 1,
+    /// The address isn't valid for this device
+    IllegalDataAddress = 2,
+    /// A value in the request isn't valid
+    IllegalDataValue = 3,
+    /// An unrecoverable error occurred while handling the request
+    ServerDeviceFailure = 4,
+}
+
+/// Data model exposed by a Modbus slave
+///
+/// Every method defaults to rejecting the address, so only the tables that the device has need
+/// to be implemented. Multi-item requests call these methods once per item, in address order.
+pub trait ModbusMap {
+    /// Read a coil
+    fn read_coil(&mut self, _addr: u16) -> Result<bool, Exception> {
+        Err(Exception::IllegalDataAddress)
+    }
+
+    /// Read a discrete input
+    fn read_discrete_input(&mut self, _addr: u16) -> Result<bool, Exception> {
+        Err(Exception::IllegalDataAddress)
+    }
+
+    /// Read a holding register
+    fn read_holding_register(&mut self, _addr: u16) -> Result<u16, Exception> {
+        Err(Exception::IllegalDataAddress)
+    }
+
+    /// Read an input register
+    fn read_input_register(&mut self, _addr: u16) -> Result<u16, Exception> {
+        Err(Exception::IllegalDataAddress)
+    }
+
+    /// Write a coil
+    fn write_coil(&mut self, _addr: u16, _value: bool) -> Result<(), Exception> {
+        Err(Exception::IllegalDataAddress)
+    }
+
+    /// Write a holding register
+    fn write_holding_register(&mut self, _addr: u16, _value: u16) -> Result<(), Exception> {
+        Err(Exception::IllegalDataAddress)
+    }
+}
+
+/// Source of received bytes and frame boundaries for `ModbusSlave`
+///
+/// Implemented by `serial::RxTimeout`.
+pub trait FrameReceiver {
+    /// Receive error type
+    type Error;
+
+    /// Return the next received byte, or `RxEvent::FrameComplete` once the line has been idle
+    /// for the frame gap after the last byte
+    fn read(&mut self) -> nb::Result<RxEvent, Self::Error>;
+}
+
+/// Compute the CRC-16/Modbus of `data`. The CRC is sent low byte first.
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, &b| {
+        let mut crc = crc ^ b as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
+
+/// Timer ticks in the 3.5 character frame gap at `baudrate`, assuming 11-bit characters. Above
+/// 19200 baud the fixed 1.75 ms gap from the Modbus specification is used instead.
+#[inline]
+pub const fn frame_gap_ticks(timer_freq: u32, baudrate: u32) -> u16 {
+    if baudrate > 19_200 {
+        let ticks = timer_freq as u64 * 1750 / 1_000_000;
+        if ticks > u16::MAX as u64 {
+            u16::MAX
+        } else {
+            ticks as u16
+        }
+    } else {
+        char_times_to_ticks(timer_freq, baudrate, 11, 35)
+    }
+}
+
+#[inline(always)]
+fn be_u16(bytes: &[u8], idx: usize) -> u16 {
+    u16::from_be_bytes([bytes[idx], bytes[idx + 1]])
+}
+
+// Check a request for `qty` items starting at `start`
+#[inline]
+fn check_range(start: u16, qty: u16, max_qty: u16) -> Result<(), Exception> {
+    if qty == 0 || qty > max_qty {
+        Err(Exception::IllegalDataValue)
+    } else if start as u32 + qty as u32 > 0x1_0000 {
+        Err(Exception::IllegalDataAddress)
+    } else {
+        Ok(())
+    }
+}
+
+// Handle a request PDU and write the response PDU into `resp`, returning its length
+fn dispatch<M: ModbusMap>(map: &mut M, req: &[u8], resp: &mut [u8]) -> Result<usize, Exception> {
+    let fc = req[0];
+    resp[0] = fc;
+    match fc {
+        // Read coils / discrete inputs
+        0x01 | 0x02 => {
+            if req.len() != 5 {
+                return Err(Exception::IllegalDataValue);
+            }
+            let (start, qty) = (be_u16(req, 1), be_u16(req, 3));
+            check_range(start, qty, 2000)?;
+            let byte_count = (qty as usize).div_ceil(8);
+            resp[1] = byte_count as u8;
+            resp[2..2 + byte_count].fill(0);
+            for i in 0..qty {
+                let bit = if fc == 0x01 {
+                    map.read_coil(start + i)?
+                } else {
+                    map.read_discrete_input(start + i)?
+                };
+                resp[2 + i as usize / 8] |= (bit as u8) << (i % 8);
+            }
+            Ok(2 + byte_count)
+        }
+        // Read holding / input registers
+        0x03 | 0x04 => {
+            if req.len() != 5 {
+                return Err(Exception::IllegalDataValue);
+            }
+            let (start, qty) = (be_u16(req, 1), be_u16(req, 3));
+            check_range(start, qty, 125)?;
+            resp[1] = (qty * 2) as u8;
+            for i in 0..qty {
+                let value = if fc == 0x03 {
+                    map.read_holding_register(start + i)?
+                } else {
+                    map.read_input_register(start + i)?
+                };
+                let idx = 2 + 2 * i as usize;
+                resp[idx..idx + 2].copy_from_slice(&value.to_be_bytes());
+            }
+            Ok(2 + 2 * qty as usize)
+        }
+        // Write single coil
+        0x05 => {
+            if req.len() != 5 {
+                return Err(Exception::IllegalDataValue);
+            }
+            let value = match be_u16(req, 3) {
+                0xFF00 => true,
+                0x0000 => false,
+                _ => return Err(Exception::IllegalDataValue),
+            };
+            map.write_coil(be_u16(req, 1), value)?;
+            resp[..5].copy_from_slice(req);
+            Ok(5)
+        }
+        // Write single register
+        0x06 => {
+            if req.len() != 5 {
+                return Err(Exception::IllegalDataValue);
+            }
+            map.write_holding_register(be_u16(req, 1), be_u16(req, 3))?;
+            resp[..5].copy_from_slice(req);
+            Ok(5)
+        }
+        // Write multiple coils
+        0x0F => {
+            if req.len() < 6 {
+                return Err(Exception::IllegalDataValue);
+            }
+            let (start, qty) = (be_u16(req, 1), be_u16(req, 3));
+            check_range(start, qty, 1968)?;
+            let byte_count = req[5] as usize;
+            if byte_count != (qty as usize).div_ceil(8) || req.len() != 6 + byte_count {
+                return Err(Exception::IllegalDataValue);
+            }
+            for i in 0..qty {
+                let bit = (req[6 + i as usize / 8] >> (i % 8)) & 1 != 0;
+                map.write_coil(start + i, bit)?;
+            }
+            resp[..5].copy_from_slice(&req[..5]);
+            Ok(5)
+        }
+        // Write multiple registers
+        0x10 => {
+            if req.len() < 6 {
+                return Err(Exception::IllegalDataValue);
+            }
+            let (start, qty) = (be_u16(req, 1), be_u16(req, 3));
+            check_range(start, qty, 123)?;
+            let byte_count = req[5] as usize;
+            if byte_count != 2 * qty as usize || req.len() != 6 + byte_count {
+                return Err(Exception::IllegalDataValue);
+            }
+            for i in 0..qty {
+                map.write_holding_register(start + i, be_u16(req, 6 + 2 * i as usize))?;
+            }
+            resp[..5].copy_from_slice(&req[..5]);
+            Ok(5)
+        }
+        _ => Err(Exception::IllegalFunction),
+    }
+}
+
+/// Handle a complete RTU frame addressed to slave `unit_id`, writing the response frame into
+/// `resp`. Returns the response length, or `None` if no response should be sent because the
+/// frame is malformed, has a bad CRC, is addressed to another slave or is a broadcast.
+pub fn process_request<M: ModbusMap>(
+    map: &mut M,
+    unit_id: u8,
+    req: &[u8],
+    resp: &mut [u8; MAX_FRAME_LEN],
+) -> Option<usize> {
+    // Address, function code and CRC at minimum
+    if req.len() < 4 || req.len() > MAX_FRAME_LEN {
+        return None;
+    }
+    let (frame, crc) = req.split_at(req.len() - 2);
+    if crc16(frame) != u16::from_le_bytes([crc[0], crc[1]]) {
+        return None;
+    }
+    let addr = frame[0];
+    if addr != unit_id && addr != BROADCAST_ADDR {
+        return None;
+    }
+
+    let pdu = &frame[1..];
+    if addr == BROADCAST_ADDR {
+        // Reads could have side effects in the map, and their result would be discarded anyway
+        if matches!(pdu[0], 0x05 | 0x06 | 0x0F | 0x10) {
+            dispatch(map, pdu, &mut resp[1..MAX_FRAME_LEN - 2]).ok();
+        }
+        return None;
+    }
+    let pdu_len = match dispatch(map, pdu, &mut resp[1..MAX_FRAME_LEN - 2]) {
+        Ok(len) => len,
+        Err(exception) => {
+            resp[1] = pdu[0] | 0x80;
+            resp[2] = exception as u8;
+            2
+        }
+    };
+
+    resp[0] = addr;
+    let len = 1 + pdu_len;
+    let crc = crc16(&resp[..len]);
+    resp[len..len + 2].copy_from_slice(&crc.to_le_bytes());
+    Some(len + 2)
+}
+
+/// Modbus RTU slave
+pub struct ModbusSlave<R, W, M> {
+    rx: R,
+    tx: W,
+    map: M,
+    unit_id: u8,
+    buf: [u8; MAX_FRAME_LEN],
+    len: usize,
+    // Set if the current frame overflowed the buffer or had a corrupted byte
+    bad_frame: bool,
+}
+
+impl<R, W, M> ModbusSlave<R, W, M>
+where
+    R: FrameReceiver,
+    W: embedded_io::Write,
+    M: ModbusMap,
+{
+    /// Create a slave with address `unit_id`. `rx` should use a gap of 3.5 characters, as
+    /// computed by `frame_gap_ticks()`.
+    #[inline]
+    pub fn new(rx: R, tx: W, map: M, unit_id: u8) -> Self {
+        ModbusSlave {
+            rx,
+            tx,
+            map,
+            unit_id,
+            buf: [0; MAX_FRAME_LEN],
+            len: 0,
+            bad_frame: false,
+        }
+    }
+
+    /// Return the receiver, transmitter and data map
+    #[inline]
+    pub fn free(self) -> (R, W, M) {
+        (self.rx, self.tx, self.map)
+    }
+
+    /// Data map
+    #[inline]
+    pub fn map(&mut self) -> &mut M {
+        &mut self.map
+    }
+
+    /// Receive bytes and handle each frame once the frame gap has passed, sending the response
+    /// if there is one. Returns `Ok` after each complete frame, whether or not it was addressed
+    /// to this slave. Sending the response blocks.
+    pub fn poll(&mut self) -> nb::Result<(), W::Error> {
+        loop {
+            match self.rx.read() {
+                Ok(RxEvent::Byte(byte)) => match self.buf.get_mut(self.len) {
+                    Some(b) => {
+                        *b = byte;
+                        self.len += 1;
+                    }
+                    None => self.bad_frame = true,
+                },
+                Err(nb::Error::Other(_)) => self.bad_frame = true,
+                Ok(RxEvent::FrameComplete) => break,
+                Err(nb::Error::WouldBlock) => return Err(nb::Error::WouldBlock),
+            }
+        }
+
+        let len = core::mem::replace(&mut self.len, 0);
+        if core::mem::replace(&mut self.bad_frame, false) {
+            return Ok(());
+        }
+        let mut resp = [0; MAX_FRAME_LEN];
+        if let Some(resp_len) =
+            process_request(&mut self.map, self.unit_id, &self.buf[..len], &mut resp)
+        {
+            self.tx.write_all(&resp[..resp_len])?;
+            self.tx.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+//! RS-485 half-duplex transmitter
+//!
+//! Wraps a serial `Tx` together with the GPIO driving the transceiver's driver enable (DE) pin.
+//! DE is asserted before the first byte of a transmission and released only once the final stop
+//! bit has left the shift register, so the bus is handed back neither early nor late. The
+//! receiver enable (RE) pin, which is active low, is usually tied to DE.
+
+use crate::gpio::{Output, Pin, PinNum, PortNum};
+use crate::serial::{SerialUsci, Tx};
+use embedded_hal::digital::v2::OutputPin;
+use embedded_hal::serial::Write;
+use embedded_hal_1::delay::DelayNs;
+use nb::block;
+use void::ResultVoidExt;
+
+/// RS-485 transmitter with driver enable control
+pub struct Rs485<USCI: SerialUsci, TXPIN, PORT: PortNum, PIN: PinNum, DELAY: DelayNs> {
+    tx: Tx<USCI, TXPIN>,
+    de: Pin<PORT, PIN, Output>,
+    delay: DELAY,
+    turnaround_us: u32,
+}
+
+impl<USCI: SerialUsci, TXPIN, PORT: PortNum, PIN: PinNum, DELAY: DelayNs>
+    Rs485<USCI, TXPIN, PORT, PIN, DELAY>
+{
+    /// Create an RS-485 transmitter. DE is deasserted (driven low).
+    ///
+    /// `turnaround_us` is how long DE is held before the first byte and after the last stop bit,
+    /// giving the transceiver and the other nodes time to switch direction.
+    #[inline]
+    pub fn new(
+        tx: Tx<USCI, TXPIN>,
+        mut de: Pin<PORT, PIN, Output>,
+        delay: DELAY,
+        turnaround_us: u32,
+    ) -> Self {
+        de.set_low().void_unwrap();
+        Rs485 {
+            tx,
+            de,
+            delay,
+            turnaround_us,
+        }
+    }
+
+    /// Return the Tx pin, DE pin and delay provider
+    #[inline]
+    pub fn free(self) -> (Tx<USCI, TXPIN>, Pin<PORT, PIN, Output>, DELAY) {
+        (self.tx, self.de, self.delay)
+    }
+
+    /// Set the turnaround delay in microseconds
+    #[inline]
+    pub fn set_turnaround_us(&mut self, turnaround_us: u32) {
+        self.turnaround_us = turnaround_us;
+    }
+
+    /// Turnaround delay in microseconds
+    #[inline]
+    pub fn turnaround_us(&self) -> u32 {
+        self.turnaround_us
+    }
+
+    /// Send `data` as a single transmission. Blocks until the final stop bit has been sent and DE
+    /// has been released.
+    pub fn send(&mut self, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        self.de.set_high().void_unwrap();
+        self.delay.delay_us(self.turnaround_us);
+
+        for &byte in data {
+            block!(self.tx.write(byte)).void_unwrap();
+        }
+        // UCBUSY only clears once the shift register is empty, unlike UCTXIFG
+        while !self.tx.is_idle() {}
+
+        self.delay.delay_us(self.turnaround_us);
+        self.de.set_low().void_unwrap();
+    }
+}
+
+impl<USCI: SerialUsci, TXPIN, PORT: PortNum, PIN: PinNum, DELAY: DelayNs> embedded_io::ErrorType
+    for Rs485<USCI, TXPIN, PORT, PIN, DELAY>
+{
+    type Error = core::convert::Infallible;
+}
+
+impl<USCI: SerialUsci, TXPIN, PORT: PortNum, PIN: PinNum, DELAY: DelayNs> embedded_io::Write
+    for Rs485<USCI, TXPIN, PORT, PIN, DELAY>
+{
+    /// Sends all of `buf` as a single transmission
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.send(buf);
+        Ok(buf.len())
+    }
+
+    /// Does nothing, since every write finishes before DE is released
+    #[inline]
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
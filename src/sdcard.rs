@@ -0,0 +1,498 @@
+//! SD/MMC card driver over SPI
+//!
+//! Requires the `sdcard` feature. Drives an SD, SDHC or MMC card in SPI mode through any
+//! embedded-hal 1.0 `SpiDevice`, such as `spi::SpiDevice` for a bus of its own or
+//! `spi::SharedSpiDevice` for a bus shared with other chips. The device must use SPI mode 0 with
+//! MSB first bit order.
+//!
+//! Each step of a command (sending it, polling for the response, transferring a data block) is a
+//! separate `SpiDevice` transaction, so chip select is briefly deasserted between steps. Cards
+//! accept this in practice, but strictly the SD specification only allows it while the card is
+//! busy programming. Likewise, the dummy clocks sent by `init()` go out with chip select asserted
+//! rather than deasserted.
+//!
+//! The card is accessed in 512 byte blocks through the `BlockDevice` trait, which filesystem
+//! libraries can be built on top of. CRC checking is enabled on the card, and all commands and
+//! data blocks are protected by CRC7 and CRC16 respectively.
+//!
+//! Timeouts are measured in bytes clocked on the bus rather than wall time, so they scale with the
+//! bus frequency.
+
+use embedded_hal_1::spi::{Error, ErrorKind, Operation, SpiDevice};
+
+/// Size of a single block in bytes
+pub const BLOCK_SIZE: usize = 512;
+
+/// A single block of data
+pub type Block = [u8; BLOCK_SIZE];
+
+/// A storage device addressed in fixed-size blocks
+pub trait BlockDevice {
+    /// Error type returned by the device
+    type Error;
+
+    /// Read consecutive blocks, starting at block number `start_block`
+    fn read_blocks(&mut self, start_block: u32, blocks: &mut [Block]) -> Result<(), Self::Error>;
+
+    /// Write consecutive blocks, starting at block number `start_block`
+    fn write_blocks(&mut self, start_block: u32, blocks: &[Block]) -> Result<(), Self::Error>;
+
+    /// Total number of blocks on the device
+    fn num_blocks(&mut self) -> Result<u32, Self::Error>;
+}
+
+/// Highest bus frequency allowed during initialization. Cards only guarantee support for up to
+/// 400 kHz until initialization is complete.
+pub const INIT_FREQ: u32 = 400_000;
+
+const CMD0_GO_IDLE_STATE: u8 = 0;
+const CMD1_SEND_OP_COND: u8 = 1;
+const CMD8_SEND_IF_COND: u8 = 8;
+const CMD9_SEND_CSD: u8 = 9;
+const CMD12_STOP_TRANSMISSION: u8 = 12;
+const CMD16_SET_BLOCKLEN: u8 = 16;
+const CMD17_READ_SINGLE_BLOCK: u8 = 17;
+const CMD18_READ_MULTIPLE_BLOCK: u8 = 18;
+const CMD24_WRITE_BLOCK: u8 = 24;
+const CMD25_WRITE_MULTIPLE_BLOCK: u8 = 25;
+const CMD55_APP_CMD: u8 = 55;
+const CMD58_READ_OCR: u8 = 58;
+const CMD59_CRC_ON_OFF: u8 = 59;
+const ACMD41_SD_SEND_OP_COND: u8 = 41;
+
+const R1_READY: u8 = 0x00;
+const R1_IDLE: u8 = 0x01;
+const R1_ILLEGAL_COMMAND: u8 = 0x04;
+
+const TOKEN_START_BLOCK: u8 = 0xFE;
+const TOKEN_START_MULTI_WRITE: u8 = 0xFC;
+const TOKEN_STOP_MULTI_WRITE: u8 = 0xFD;
+const DATA_RESPONSE_MASK: u8 = 0x1F;
+const DATA_RESPONSE_ACCEPTED: u8 = 0x05;
+
+// CMD8 argument: 2.7-3.6V supply range and a check pattern that the card echoes back
+const IF_COND_ARG: u32 = 0x1AA;
+// ACMD41 argument telling the card the host supports high capacity cards
+const OP_COND_HCS: u32 = 1 << 30;
+
+const CMD0_RETRIES: u8 = 10;
+const R1_RETRIES: u8 = 10;
+const INIT_RETRIES: u16 = 10_000;
+const READ_TOKEN_RETRIES: u32 = 100_000;
+const BUSY_RETRIES: u32 = 500_000;
+
+/// Kind of card detected during initialization
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardType {
+    /// MultiMediaCard
+    Mmc,
+    /// SD version 1.x standard capacity card
+    SdV1,
+    /// SD version 2.0+ standard capacity card
+    SdV2,
+    /// SD version 2.0+ high or extended capacity card (SDHC/SDXC)
+    Sdhc,
+}
+
+/// SD card errors
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum SdCardError {
+    /// The underlying SPI device reported an error
+    Spi(ErrorKind),
+    /// The card did not respond in time
+    Timeout,
+    /// The card returned an error response to a command
+    Command {
+        /// Command index
+        cmd: u8,
+        /// R1 response byte
+        r1: u8,
+    },
+    /// The card is not a supported type or doesn't support the 2.7-3.6V supply range
+    Unsupported,
+    /// A data block was received with an invalid CRC
+    Crc,
+    /// The card returned an error token instead of a data block. Contains the token.
+    ReadError(u8),
+    /// The card rejected a written data block. Contains the data response token.
+    WriteRejected(u8),
+    /// The card hasn't been initialized with `init()`
+    NotInitialized,
+    /// The card's CSD register holds values that don't describe a valid card size
+    InvalidCsd,
+}
+
+#[inline(always)]
+fn spi_err<E: Error>(err: E) -> SdCardError {
+    SdCardError::Spi(err.kind())
+}
+
+/// An SD/MMC card connected to an SPI device
+pub struct SdCard<SPI> {
+    spi: SPI,
+    card_type: Option<CardType>,
+}
+
+impl<SPI: SpiDevice> SdCard<SPI> {
+    /// Create a new SD card driver. The card must be initialized with `init()` before use.
+    #[inline]
+    pub fn new(spi: SPI) -> Self {
+        SdCard {
+            spi,
+            card_type: None,
+        }
+    }
+
+    /// Return the SPI device
+    #[inline]
+    pub fn free(self) -> SPI {
+        self.spi
+    }
+
+    /// Access the SPI device, e.g. to raise its bus frequency after `init()`
+    #[inline]
+    pub fn device_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
+    /// The type of the card, if it has been initialized
+    #[inline]
+    pub fn card_type(&self) -> Option<CardType> {
+        self.card_type
+    }
+
+    /// Initialize the card. The SPI device must run at `INIT_FREQ` or below while this runs.
+    /// Afterwards its frequency can be raised to up to 25 MHz for SD cards, e.g. with
+    /// `SharedSpiDevice::set_config()` through `device_mut()`.
+    pub fn init(&mut self) -> Result<CardType, SdCardError> {
+        self.card_type = None;
+
+        // The card needs at least 74 clock cycles to enter SPI mode
+        self.spi.write(&[0xFF; 10]).map_err(spi_err)?;
+
+        let card_type = self.init_card()?;
+        self.card_type = Some(card_type);
+        Ok(card_type)
+    }
+
+    fn init_card(&mut self) -> Result<CardType, SdCardError> {
+        let mut tries = 0;
+        while self.command(CMD0_GO_IDLE_STATE, 0)? != R1_IDLE {
+            tries += 1;
+            if tries == CMD0_RETRIES {
+                return Err(SdCardError::Timeout);
+            }
+        }
+
+        self.command_ok(CMD59_CRC_ON_OFF, 1)?;
+
+        let r1 = self.command(CMD8_SEND_IF_COND, IF_COND_ARG)?;
+        let card_type = if r1 & R1_ILLEGAL_COMMAND != 0 {
+            // Version 1 cards and MMCs don't know CMD8. MMCs don't know ACMD41 either.
+            match self.app_op_cond(0) {
+                Ok(()) => CardType::SdV1,
+                Err(SdCardError::Command { .. }) => {
+                    self.op_cond_mmc()?;
+                    CardType::Mmc
+                }
+                Err(err) => return Err(err),
+            }
+        } else {
+            let mut r7 = [0; 4];
+            self.spi.read(&mut r7).map_err(spi_err)?;
+            if r7[2] & 0x0F != 0x01 || r7[3] != 0xAA {
+                return Err(SdCardError::Unsupported);
+            }
+            self.app_op_cond(OP_COND_HCS)?;
+
+            self.command_ok(CMD58_READ_OCR, 0)?;
+            let mut ocr = [0; 4];
+            self.spi.read(&mut ocr).map_err(spi_err)?;
+            // Card capacity status bit
+            if ocr[0] & 0x40 != 0 {
+                CardType::Sdhc
+            } else {
+                CardType::SdV2
+            }
+        };
+
+        // High capacity cards always use 512 byte blocks
+        if card_type != CardType::Sdhc {
+            self.command_ok(CMD16_SET_BLOCKLEN, BLOCK_SIZE as u32)?;
+        }
+        Ok(card_type)
+    }
+
+    fn app_op_cond(&mut self, arg: u32) -> Result<(), SdCardError> {
+        for _ in 0..INIT_RETRIES {
+            self.command(CMD55_APP_CMD, 0)?;
+            match self.command(ACMD41_SD_SEND_OP_COND, arg)? {
+                R1_READY => return Ok(()),
+                R1_IDLE => (),
+                r1 => {
+                    return Err(SdCardError::Command {
+                        cmd: ACMD41_SD_SEND_OP_COND,
+                        r1,
+                    })
+                }
+            }
+        }
+        Err(SdCardError::Timeout)
+    }
+
+    fn op_cond_mmc(&mut self) -> Result<(), SdCardError> {
+        for _ in 0..INIT_RETRIES {
+            match self.command(CMD1_SEND_OP_COND, 0)? {
+                R1_READY => return Ok(()),
+                R1_IDLE => (),
+                r1 => {
+                    return Err(SdCardError::Command {
+                        cmd: CMD1_SEND_OP_COND,
+                        r1,
+                    })
+                }
+            }
+        }
+        Err(SdCardError::Timeout)
+    }
+
+    /// Read a single block
+    #[inline]
+    pub fn read_block(&mut self, block_num: u32, block: &mut Block) -> Result<(), SdCardError> {
+        self.read_blocks(block_num, core::slice::from_mut(block))
+    }
+
+    /// Write a single block
+    #[inline]
+    pub fn write_block(&mut self, block_num: u32, block: &Block) -> Result<(), SdCardError> {
+        self.write_blocks(block_num, core::slice::from_ref(block))
+    }
+
+    /// Read consecutive blocks. Multiple blocks are read with a single multi-block read command.
+    pub fn read_blocks(&mut self, start_block: u32, blocks: &mut [Block]) -> Result<(), SdCardError> {
+        let addr = self.block_address(start_block)?;
+        match blocks.len() {
+            0 => Ok(()),
+            1 => {
+                self.command_ok(CMD17_READ_SINGLE_BLOCK, addr)?;
+                self.read_data(&mut blocks[0])
+            }
+            _ => {
+                self.command_ok(CMD18_READ_MULTIPLE_BLOCK, addr)?;
+                let res = blocks.iter_mut().try_for_each(|block| self.read_data(block));
+                // Always stop the transmission, even if a block failed
+                let stop = self.command(CMD12_STOP_TRANSMISSION, 0);
+                res?;
+                stop.map(|_| ())
+            }
+        }
+    }
+
+    /// Write consecutive blocks. Multiple blocks are written with a single multi-block write command.
+    pub fn write_blocks(&mut self, start_block: u32, blocks: &[Block]) -> Result<(), SdCardError> {
+        let addr = self.block_address(start_block)?;
+        match blocks.len() {
+            0 => Ok(()),
+            1 => {
+                self.command_ok(CMD24_WRITE_BLOCK, addr)?;
+                self.write_data(TOKEN_START_BLOCK, &blocks[0])
+            }
+            _ => {
+                self.command_ok(CMD25_WRITE_MULTIPLE_BLOCK, addr)?;
+                let res = blocks
+                    .iter()
+                    .try_for_each(|block| self.write_data(TOKEN_START_MULTI_WRITE, block));
+                // Always stop the transmission, even if a block failed
+                self.spi
+                    .write(&[TOKEN_STOP_MULTI_WRITE, 0xFF])
+                    .map_err(spi_err)?;
+                let busy = self.wait_ready();
+                res?;
+                busy
+            }
+        }
+    }
+
+    /// Total number of 512 byte blocks on the card, read from its CSD register
+    pub fn num_blocks(&mut self) -> Result<u32, SdCardError> {
+        let card_type = self.card_type.ok_or(SdCardError::NotInitialized)?;
+        let mut csd = [0; 16];
+        self.command_ok(CMD9_SEND_CSD, 0)?;
+        self.read_data(&mut csd)?;
+
+        // MMCs use the version 1 layout regardless of their CSD_STRUCTURE field
+        match (card_type, csd[0] >> 6) {
+            (CardType::Mmc, _) | (_, 0) => {
+                // Only 512, 1024 and 2048 byte blocks are valid
+                let read_bl_len = (csd[5] & 0x0F) as u32;
+                if !(9..=11).contains(&read_bl_len) {
+                    return Err(SdCardError::InvalidCsd);
+                }
+                let c_size = ((csd[6] & 0x03) as u32) << 10
+                    | (csd[7] as u32) << 2
+                    | (csd[8] >> 6) as u32;
+                let c_size_mult = (((csd[9] & 0x03) << 1) | (csd[10] >> 7)) as u32;
+                Ok((c_size + 1) << (c_size_mult + 2 + read_bl_len - 9))
+            }
+            (_, 1) => {
+                let c_size =
+                    ((csd[7] & 0x3F) as u32) << 16 | (csd[8] as u32) << 8 | csd[9] as u32;
+                // A C_SIZE of 0x3FFFFF would give 2^32 blocks, past what a u32 block count holds
+                (c_size + 1).checked_mul(1024).ok_or(SdCardError::InvalidCsd)
+            }
+            _ => Err(SdCardError::Unsupported),
+        }
+    }
+
+    // Standard capacity cards are byte addressed, high capacity cards are block addressed
+    #[inline]
+    fn block_address(&self, block_num: u32) -> Result<u32, SdCardError> {
+        match self.card_type {
+            Some(CardType::Sdhc) => Ok(block_num),
+            Some(_) => Ok(block_num * BLOCK_SIZE as u32),
+            None => Err(SdCardError::NotInitialized),
+        }
+    }
+
+    #[inline]
+    fn read_byte(&mut self) -> Result<u8, SdCardError> {
+        let mut byte = [0];
+        self.spi.read(&mut byte).map_err(spi_err)?;
+        Ok(byte[0])
+    }
+
+    // The card holds MISO low while it's busy
+    fn wait_ready(&mut self) -> Result<(), SdCardError> {
+        for _ in 0..BUSY_RETRIES {
+            if self.read_byte()? == 0xFF {
+                return Ok(());
+            }
+        }
+        Err(SdCardError::Timeout)
+    }
+
+    // Send a command and return its R1 response
+    fn command(&mut self, cmd: u8, arg: u32) -> Result<u8, SdCardError> {
+        // The card may still be in native mode before CMD0, where it doesn't signal busy
+        if cmd != CMD0_GO_IDLE_STATE {
+            self.wait_ready()?;
+        }
+
+        let [a0, a1, a2, a3] = arg.to_be_bytes();
+        let mut frame = [0x40 | cmd, a0, a1, a2, a3, 0];
+        frame[5] = (crc7(&frame[..5]) << 1) | 1;
+        self.spi.write(&frame).map_err(spi_err)?;
+
+        // Skip the stuff byte following CMD12
+        if cmd == CMD12_STOP_TRANSMISSION {
+            self.read_byte()?;
+        }
+
+        for _ in 0..R1_RETRIES {
+            let r1 = self.read_byte()?;
+            if r1 & 0x80 == 0 {
+                return Ok(r1);
+            }
+        }
+        Err(SdCardError::Timeout)
+    }
+
+    // Send a command, failing if the response has any error bits set
+    #[inline]
+    fn command_ok(&mut self, cmd: u8, arg: u32) -> Result<(), SdCardError> {
+        match self.command(cmd, arg)? {
+            R1_READY | R1_IDLE => Ok(()),
+            r1 => Err(SdCardError::Command { cmd, r1 }),
+        }
+    }
+
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<(), SdCardError> {
+        let mut token = 0xFF;
+        for _ in 0..READ_TOKEN_RETRIES {
+            token = self.read_byte()?;
+            if token != 0xFF {
+                break;
+            }
+        }
+        match token {
+            TOKEN_START_BLOCK => (),
+            0xFF => return Err(SdCardError::Timeout),
+            token => return Err(SdCardError::ReadError(token)),
+        }
+
+        let mut crc = [0; 2];
+        self.spi
+            .transaction(&mut [Operation::Read(buf), Operation::Read(&mut crc)])
+            .map_err(spi_err)?;
+        if u16::from_be_bytes(crc) != crc16(buf) {
+            return Err(SdCardError::Crc);
+        }
+        Ok(())
+    }
+
+    fn write_data(&mut self, token: u8, buf: &[u8]) -> Result<(), SdCardError> {
+        self.spi
+            .transaction(&mut [
+                Operation::Write(&[token]),
+                Operation::Write(buf),
+                Operation::Write(&crc16(buf).to_be_bytes()),
+            ])
+            .map_err(spi_err)?;
+
+        let response = self.read_byte()? & DATA_RESPONSE_MASK;
+        if response != DATA_RESPONSE_ACCEPTED {
+            return Err(SdCardError::WriteRejected(response));
+        }
+        self.wait_ready()
+    }
+}
+
+impl<SPI: SpiDevice> BlockDevice for SdCard<SPI> {
+    type Error = SdCardError;
+
+    #[inline]
+    fn read_blocks(&mut self, start_block: u32, blocks: &mut [Block]) -> Result<(), Self::Error> {
+        SdCard::read_blocks(self, start_block, blocks)
+    }
+
+    #[inline]
+    fn write_blocks(&mut self, start_block: u32, blocks: &[Block]) -> Result<(), Self::Error> {
+        SdCard::write_blocks(self, start_block, blocks)
+    }
+
+    #[inline]
+    fn num_blocks(&mut self) -> Result<u32, Self::Error> {
+        SdCard::num_blocks(self)
+    }
+}
+
+/// CRC7 used to protect SD commands (polynomial x^7 + x^3 + 1)
+fn crc7(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in data {
+        for i in 0..8 {
+            crc <<= 1;
+            if ((byte << i) ^ crc) & 0x80 != 0 {
+                crc ^= 0x09;
+            }
+        }
+    }
+    crc & 0x7F
+}
+
+/// CRC16-CCITT used to protect SD data blocks (polynomial x^16 + x^12 + x^5 + 1, initial value 0)
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
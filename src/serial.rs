@@ -5,17 +5,30 @@
 //! converting the appropriate GPIO pins to the alternate function corresponding to UART.
 //!
 //! The Tx and Rx pins are used to send and receive bytes via serial connection.
+//!
+//! Passing both pins to `Tx::release()` puts the eUSCI back into reset and returns the PAC
+//! peripheral and GPIO pins, so the same eUSCI_A can later be reconfigured, e.g. as an SPI bus.
+//! Halves created on their own with `tx_only()` or `rx_only()` are released with
+//! `Tx::release_tx_only()` or `Rx::release_rx_only()` instead.
+
+mod baud;
+mod timing;
+
+pub use baud::{baud_rate_error, BaudRateError};
+pub use timing::{char_times_to_ticks, RxEvent};
 
+use baud::{calculate_baud_config, frame_baud_rate_error, BaudConfig};
 use crate::clock::{Aclk, Clock, Smclk};
 use crate::gpio::{Alternate1, Pin, Pin1, Pin2, Pin3, Pin5, Pin6, Pin7, P1, P4};
-use crate::hw_traits::eusci::{EUsciUart, UartUcxStatw, UcaCtlw0, Ucssel};
+use crate::hw_traits::eusci::{EUsciUart, UartUcmode, UartUcxStatw, UcaCtlw0, UcaIrctl, Ucssel};
+use crate::timer::{CapCmp, SubTimer, TimerPeriph};
 use core::marker::PhantomData;
 use core::num::NonZeroU32;
 use embedded_hal::serial::{Read, Write};
 use msp430fr2355 as pac;
 
 /// Bit order of transmit and receive
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum BitOrder {
     /// LSB first (typically the default)
     LsbFirst,
@@ -25,7 +38,7 @@ pub enum BitOrder {
 
 impl BitOrder {
     #[inline(always)]
-    fn to_bool(self) -> bool {
+    pub(crate) fn to_bool(self) -> bool {
         match self {
             BitOrder::LsbFirst => false,
             BitOrder::MsbFirst => true,
@@ -44,7 +57,7 @@ pub enum BitCount {
 
 impl BitCount {
     #[inline(always)]
-    fn to_bool(self) -> bool {
+    pub(crate) fn to_bool(self) -> bool {
         match self {
             BitCount::EightBits => false,
             BitCount::SevenBits => true,
@@ -120,6 +133,127 @@ impl Loopback {
     }
 }
 
+/// Receive deglitch time. Pulses on Rx shorter than this are ignored.
+#[derive(Clone, Copy)]
+pub enum Deglitch {
+    /// Approximately 5 ns
+    Ns5,
+    /// Approximately 20 ns
+    Ns20,
+    /// Approximately 30 ns
+    Ns30,
+    /// Approximately 50 ns (hardware reset value)
+    Ns50,
+}
+
+impl Deglitch {
+    #[inline(always)]
+    fn ucglit(self) -> u8 {
+        match self {
+            Deglitch::Ns5 => 0,
+            Deglitch::Ns20 => 1,
+            Deglitch::Ns30 => 2,
+            Deglitch::Ns50 => 3,
+        }
+    }
+}
+
+/// Whether received characters with errors are passed on to `Rx`
+#[derive(Clone, Copy)]
+pub enum ErrorPolicy {
+    /// The character sets the Rx flag and is reported as an error when read
+    Report,
+    /// The character is dropped by the hardware without setting the Rx flag
+    Discard,
+}
+
+impl ErrorPolicy {
+    #[inline(always)]
+    fn to_bool(self) -> bool {
+        match self {
+            ErrorPolicy::Report => true,
+            ErrorPolicy::Discard => false,
+        }
+    }
+}
+
+/// IrDA transmit pulse width
+#[derive(Clone, Copy)]
+pub enum IrdaPulse {
+    /// 3/16 of a bit period, as required by the IrDA SIR standard. This uses the oversampling
+    /// clock, so the UART clock must be at least 16 times the baud rate.
+    ThreeSixteenths,
+    /// Fixed pulse width of `(n + 1) / (2 * f_clk)` seconds, where `f_clk` is the UART clock.
+    /// `n` is capped at 63. Lets the pulse be shortened to save power.
+    Clock(u8),
+}
+
+/// Polarity of the pulses coming from the IrDA transceiver
+#[derive(Clone, Copy)]
+pub enum IrdaRxPolarity {
+    /// The transceiver outputs a high pulse when it detects light
+    ActiveHigh,
+    /// The transceiver outputs a low pulse when it detects light (most common)
+    ActiveLow,
+}
+
+/// IrDA SIR encoder/decoder configuration
+///
+/// With IrDA enabled, `Tx` sends each zero bit as a short high pulse and each one bit as no pulse,
+/// and `Rx` decodes pulses from the transceiver back into bits. Otherwise `Tx` and `Rx` behave as
+/// a normal UART. IR links are half duplex and many transceivers see their own transmissions, so
+/// bytes received while sending should usually be discarded.
+#[derive(Clone, Copy)]
+pub struct IrdaConfig {
+    /// Transmit pulse width
+    pub tx_pulse: IrdaPulse,
+    /// Receive pulse polarity
+    pub rx_polarity: IrdaRxPolarity,
+    /// Receive glitch filter. Pulses shorter than `(n + 4) / (2 * f_clk)` seconds are ignored,
+    /// where `f_clk` is the UART clock. `n` is capped at 63. `None` disables the filter.
+    pub rx_filter: Option<u8>,
+}
+
+impl Default for IrdaConfig {
+    /// Standard 3/16 pulses, active low receive pulses and no filter
+    #[inline]
+    fn default() -> Self {
+        IrdaConfig {
+            tx_pulse: IrdaPulse::ThreeSixteenths,
+            rx_polarity: IrdaRxPolarity::ActiveLow,
+            rx_filter: None,
+        }
+    }
+}
+
+impl IrdaConfig {
+    #[inline(always)]
+    fn to_reg(self) -> UcaIrctl {
+        let (ucirtxclk, ucirtxpl) = match self.tx_pulse {
+            // (5 + 1) / (2 * 16 * baud) = 3/16 bit period
+            IrdaPulse::ThreeSixteenths => (true, 5),
+            IrdaPulse::Clock(n) => (false, n.min(63)),
+        };
+        UcaIrctl {
+            uciren: true,
+            ucirtxclk,
+            ucirtxpl,
+            ucirrxfe: self.rx_filter.is_some(),
+            ucirrxpl: matches!(self.rx_polarity, IrdaRxPolarity::ActiveLow),
+            ucirrxfl: self.rx_filter.unwrap_or(0).min(63),
+        }
+    }
+}
+
+/// Multiprocessor format used to tell address characters apart from data on a shared bus
+#[derive(Clone, Copy)]
+pub enum MultiprocessorMode {
+    /// Address characters follow an idle period of at least 10 bit times
+    IdleLine,
+    /// Each character has an extra bit after the data bits, which is set for address characters
+    AddressBit,
+}
+
 /// Marks a USCI type that can be used as a serial UART
 pub trait SerialUsci: EUsciUart {
     /// Pin used for serial UCLK
@@ -186,6 +320,8 @@ pub struct NoClockSet {
 pub struct ClockSet {
     baud_config: BaudConfig,
     clksel: Ucssel,
+    clk_freq: u32,
+    baudrate: NonZeroU32,
 }
 
 /// Builder object for configuring a serial UART
@@ -199,6 +335,11 @@ pub struct SerialConfig<USCI: SerialUsci, S> {
     stopbits: StopBits,
     parity: Parity,
     loopback: Loopback,
+    mode: UartUcmode,
+    irda: Option<IrdaConfig>,
+    deglitch: Deglitch,
+    corrupted: ErrorPolicy,
+    breaks: ErrorPolicy,
     state: S,
 }
 
@@ -211,6 +352,11 @@ macro_rules! serial_config {
             stopbits: $conf.stopbits,
             parity: $conf.parity,
             loopback: $conf.loopback,
+            mode: $conf.mode,
+            irda: $conf.irda,
+            deglitch: $conf.deglitch,
+            corrupted: $conf.corrupted,
+            breaks: $conf.breaks,
             state: $state,
         }
     };
@@ -235,6 +381,11 @@ impl<USCI: SerialUsci> SerialConfig<USCI, NoClockSet> {
             stopbits,
             parity,
             loopback,
+            mode: UartUcmode::Uart,
+            irda: None,
+            deglitch: Deglitch::Ns50,
+            corrupted: ErrorPolicy::Report,
+            breaks: ErrorPolicy::Discard,
             usci,
             state: NoClockSet {
                 baudrate: NonZeroU32::new(baudrate).unwrap_or(ONE),
@@ -255,6 +406,8 @@ impl<USCI: SerialUsci> SerialConfig<USCI, NoClockSet> {
             ClockSet {
                 baud_config: calculate_baud_config(freq, self.state.baudrate),
                 clksel: Ucssel::Uclk,
+                clk_freq: freq,
+                baudrate: self.state.baudrate,
             }
         )
     }
@@ -267,6 +420,8 @@ impl<USCI: SerialUsci> SerialConfig<USCI, NoClockSet> {
             ClockSet {
                 baud_config: calculate_baud_config(aclk.freq() as u32, self.state.baudrate),
                 clksel: Ucssel::Aclk,
+                clk_freq: aclk.freq() as u32,
+                baudrate: self.state.baudrate,
             }
         )
     }
@@ -279,119 +434,94 @@ impl<USCI: SerialUsci> SerialConfig<USCI, NoClockSet> {
             ClockSet {
                 baud_config: calculate_baud_config(smclk.freq(), self.state.baudrate),
                 clksel: Ucssel::Smclk,
+                clk_freq: smclk.freq(),
+                baudrate: self.state.baudrate,
             }
         )
     }
 }
 
-struct BaudConfig {
-    br: u16,
-    brs: u8,
-    brf: u8,
-    ucos16: bool,
-}
-
-#[inline]
-fn calculate_baud_config(clk_freq: u32, bps: NonZeroU32) -> BaudConfig {
-    // Ensure n stays within the 16 bit boundary
-    let n = (clk_freq / bps).clamp(1, 0xFFFF);
+impl<USCI: SerialUsci, S> SerialConfig<USCI, S> {
+    /// Enable automatic baud rate detection. The receiver measures the baud rate from a break
+    /// followed by a 0x55 sync byte (as used by LIN) and updates the baud rate registers, which
+    /// applies to both Rx and Tx. The baud rate passed to `new()` is used until the first sync.
+    ///
+    /// The measured rate is reported by `Rx::read_sync()`. Detection stays active until
+    /// `Rx::set_baudrate()` is called.
+    ///
+    /// Replaces any multiprocessor mode set by `multiprocessor()`.
+    #[inline(always)]
+    pub fn auto_baud(mut self) -> Self {
+        self.mode = UartUcmode::AutoBaud;
+        self
+    }
 
-    let brs = lookup_brs(clk_freq, bps);
+    /// Enable a multiprocessor mode for multi-drop networks. Address characters are sent with
+    /// `Tx::write_address()`. `Rx::sleep()` makes the receiver ignore everything except address
+    /// characters, so nodes only need to wake for messages addressed to them.
+    ///
+    /// Replaces automatic baud rate detection if it was enabled by `auto_baud()`.
+    #[inline(always)]
+    pub fn multiprocessor(mut self, mode: MultiprocessorMode) -> Self {
+        self.mode = match mode {
+            MultiprocessorMode::IdleLine => UartUcmode::IdleLineMultiproc,
+            MultiprocessorMode::AddressBit => UartUcmode::AddrBitMultiproc,
+        };
+        self
+    }
 
-    if (n >= 16) && (bps.get() < u32::MAX / 16) {
-        //  div = bps * 16
-        const SIXTEEN: NonZeroU32 = NonZeroU32::new(16).unwrap();
-        let div = bps.saturating_mul(SIXTEEN);
+    /// Set the receive deglitch time, which filters out noise spikes on the Rx line. Defaults
+    /// to `Deglitch::Ns50`, the hardware reset value.
+    #[inline(always)]
+    pub fn deglitch(mut self, deglitch: Deglitch) -> Self {
+        self.deglitch = deglitch;
+        self
+    }
 
-        // n / 16, but more precise
-        let br = (clk_freq / div) as u16;
+    /// Choose whether characters received with framing or parity errors are reported as
+    /// `RecvError`s or silently dropped. Defaults to `ErrorPolicy::Report`.
+    #[inline(always)]
+    pub fn corrupted_bytes(mut self, policy: ErrorPolicy) -> Self {
+        self.corrupted = policy;
+        self
+    }
 
-        // same as n % 16, but more precise
-        let brf = ((clk_freq % div) / bps) as u8;
-        BaudConfig {
-            ucos16: true,
-            br,
-            brf,
-            brs,
-        }
-    } else {
-        BaudConfig {
-            ucos16: false,
-            br: n as u16,
-            brf: 0,
-            brs,
-        }
+    /// Choose whether received breaks are reported as `RecvError::Break` or dropped. Dropped
+    /// breaks can still be seen with `Rx::break_detected()` until the next byte is read. Defaults
+    /// to `ErrorPolicy::Discard`.
+    #[inline(always)]
+    pub fn breaks(mut self, policy: ErrorPolicy) -> Self {
+        self.breaks = policy;
+        self
     }
-}
 
-#[inline(always)]
-fn lookup_brs(clk_freq: u32, bps: NonZeroU32) -> u8 {
-    // bps is between [1, 5_000_000] (datasheet max)
-    // clk_freq is between [0, 24_000_000] (datasheet max)
-
-    // modulo = clk_freq % bps => modulo is between [0, 4_999_999]
-    let modulo = clk_freq % bps;
-
-    // fraction = modulo * 10_000 / (bps), so within [0, ((bps-1) * 10_000) / bps].
-    // To prove upper bound we note `(bps-1)/bps` is largest when bps == 5_000_000:
-    // (4_999_999 * 10_000) / 5_000_000 = 49_999_990_000 (watch out for overflow!) / 5_000_000 = 9999.99... truncated to 9_999 because integer division
-    // So fraction is within [0, 9999]
-    let fraction_as_ten_thousandths = if modulo < u32::MAX/10_000 {
-        // Most accurate
-        ((modulo * 10_000) / bps) as u16
-    }
-    else { 
-        // Avoid overflow if modulo is large. Assume modulo < 5_000_000 from datasheet max
-        (((modulo * 500) / bps) * 20) as u16
-    };
-    
-    // See Table 22-4 from MSP430FR4xx and MSP430FR2xx family user's guide (Rev. I)
-    match fraction_as_ten_thousandths {
-        0..529     => 0x00,
-        529..715   => 0x01,
-        715..835   => 0x02,
-        835..1001  => 0x04,
-        1001..1252 => 0x08,
-        1252..1430 => 0x10,
-        1430..1670 => 0x20,
-        1670..2147 => 0x11,
-        2147..2224 => 0x21,
-        2224..2503 => 0x22,
-        2503..3000 => 0x44,
-        3000..3335 => 0x25,
-        3335..3575 => 0x49,
-        3575..3753 => 0x4A,
-        3753..4003 => 0x52,
-        4003..4286 => 0x92,
-        4286..4378 => 0x53,
-        4378..5002 => 0x55,
-        5002..5715 => 0xAA,
-        5715..6003 => 0x6B,
-        6003..6254 => 0xAD,
-        6254..6432 => 0xB5,
-        6432..6667 => 0xB6,
-        6667..7001 => 0xD6,
-        7001..7147 => 0xB7,
-        7147..7503 => 0xBB,
-        7503..7861 => 0xDD,
-        7861..8004 => 0xED,
-        8004..8333 => 0xEE,
-        8333..8464 => 0xBF,
-        8464..8572 => 0xDF,
-        8572..8751 => 0xEF,
-        8751..9004 => 0xF7,
-        9004..9170 => 0xFB,
-        9170..9288 => 0xFD,
-        9288..     => 0xFE,
+    /// Enable the IrDA SIR encoder and decoder. See `IrdaConfig` for how this affects `Tx` and
+    /// `Rx`.
+    #[inline(always)]
+    pub fn irda(mut self, config: IrdaConfig) -> Self {
+        self.irda = Some(config);
+        self
     }
 }
 
 impl<USCI: SerialUsci> SerialConfig<USCI, ClockSet> {
+    /// Baud rate accuracy of this configuration, for its frame format
+    #[inline]
+    pub fn baud_rate_error(&self) -> BaudRateError {
+        let frame_bits = 1
+            + if self.cnt.to_bool() { 7 } else { 8 }
+            + self.parity.ucpen() as u32
+            + if self.stopbits.to_bool() { 2 } else { 1 };
+        frame_baud_rate_error(self.state.clk_freq, self.state.baudrate.get(), frame_bits)
+    }
+
     #[inline]
-    fn config_hw(self) {
+    fn config_hw(self) -> u32 {
         let ClockSet {
             baud_config,
             clksel,
+            clk_freq,
+            ..
         } = self.state;
         let usci = self.usci;
 
@@ -399,6 +529,19 @@ impl<USCI: SerialUsci> SerialConfig<USCI, ClockSet> {
         usci.brw_settings(baud_config.br);
         usci.mctlw_settings(baud_config.ucos16, baud_config.brs, baud_config.brf);
         usci.loopback(self.loopback.to_bool());
+        usci.abctl_settings(matches!(self.mode, UartUcmode::AutoBaud));
+        usci.irctl_settings(match self.irda {
+            Some(irda) => irda.to_reg(),
+            None => UcaIrctl {
+                uciren: false,
+                ucirtxclk: false,
+                ucirtxpl: 0,
+                ucirrxfe: false,
+                ucirrxpl: false,
+                ucirrxfl: 0,
+            },
+        });
+        usci.ctl1_settings(self.deglitch.ucglit());
         usci.ctl0_settings(UcaCtlw0 {
             ucpen: self.parity.ucpen(),
             ucpar: self.parity.ucpar(),
@@ -406,41 +549,58 @@ impl<USCI: SerialUsci> SerialConfig<USCI, ClockSet> {
             uc7bit: self.cnt.to_bool(),
             ucspb: self.stopbits.to_bool(),
             ucssel: clksel,
-            // We want erroneous bytes to trigger RXIFG so all errors can be caught
-            ucrxeie: true,
+            ucmode: self.mode,
+            ucrxeie: self.corrupted.to_bool(),
+            ucbrkie: self.breaks.to_bool(),
         });
+        clk_freq
     }
 
     /// Perform hardware configuration and split into Tx and Rx pins from appropriate GPIOs
     #[inline]
     pub fn split<T: Into<USCI::TxPin>, R: Into<USCI::RxPin>>(
         self,
-        _tx: T,
-        _rx: R,
-    ) -> (Tx<USCI>, Rx<USCI>) {
-        self.config_hw();
-        (Tx(PhantomData), Rx(PhantomData))
+        tx: T,
+        rx: R,
+    ) -> (Tx<USCI, T>, Rx<USCI, R>) {
+        let clk_freq = self.config_hw();
+        (Tx(tx, PhantomData), Rx::new(rx, clk_freq))
     }
 
     /// Perform hardware configuration and create Tx pin from appropriate GPIO
     #[inline]
-    pub fn tx_only<T: Into<USCI::TxPin>>(self, _tx: T) -> Tx<USCI> {
+    pub fn tx_only<T: Into<USCI::TxPin>>(self, tx: T) -> Tx<USCI, Unpaired<T>> {
         self.config_hw();
-        Tx(PhantomData)
+        Tx(Unpaired(tx), PhantomData)
     }
 
     /// Perform hardware configuration and create Rx pin from appropriate GPIO
     #[inline]
-    pub fn rx_only<R: Into<USCI::RxPin>>(self, _rx: R) -> Rx<USCI> {
-        self.config_hw();
-        Rx(PhantomData)
+    pub fn rx_only<R: Into<USCI::RxPin>>(self, rx: R) -> Rx<USCI, Unpaired<R>> {
+        Rx::new(Unpaired(rx), self.config_hw())
     }
 }
 
-/// Serial transmitter pin
-pub struct Tx<USCI: SerialUsci>(PhantomData<USCI>);
+/// GPIO pin of a `Tx` or `Rx` created without the other half, by `SerialConfig::tx_only()` or
+/// `SerialConfig::rx_only()`
+pub struct Unpaired<PIN>(PIN);
+
+/// Serial transmitter pin. `PIN` is the GPIO pin it was created from.
+pub struct Tx<USCI: SerialUsci, PIN>(PIN, PhantomData<USCI>);
+
+impl<USCI: SerialUsci, PIN> Tx<USCI, PIN> {
+    /// Put the eUSCI back into reset and return the PAC peripheral along with the Tx and Rx GPIO
+    /// pins, exactly as they were passed to `SerialConfig::split()`. The pins stay in alternate
+    /// function 1, so they can be passed straight to another eUSCI configuration, such as
+    /// `SpiBusConfig`. Any byte still being shifted out is cut off, so use `flush()` or
+    /// `is_idle()` first.
+    #[inline]
+    pub fn release<RPIN>(self, rx: Rx<USCI, RPIN>) -> (USCI, PIN, RPIN) {
+        let usci = unsafe { USCI::steal() };
+        usci.ctl0_reset();
+        (usci, self.0, rx.pin)
+    }
 
-impl<USCI: SerialUsci> Tx<USCI> {
     /// Enable Tx interrupts, which fire when ready to send.
     #[inline(always)]
     pub fn enable_tx_interrupts(&mut self) {
@@ -454,9 +614,52 @@ impl<USCI: SerialUsci> Tx<USCI> {
         let usci = unsafe { USCI::steal() };
         usci.txie_clear();
     }
+
+    /// Whether the Tx buffer is empty and the last byte, including its stop bits, has been shifted
+    /// out. Unlike `flush()`, this isn't affected by errata USCI42. Since UCBUSY is also set
+    /// while receiving, this stays false while a byte is coming in.
+    #[inline]
+    pub fn is_idle(&self) -> bool {
+        let usci = unsafe { USCI::steal() };
+        usci.txifg_rd() && !usci.statw_rd().ucbusy()
+    }
+
+    /// Send an address character. Requires a multiprocessor mode to be enabled with
+    /// `SerialConfig::multiprocessor()`. In idle-line mode an idle period is sent before the
+    /// character, and in address-bit mode the character's address bit is set. Bytes sent with
+    /// `write()` are data characters.
+    #[inline]
+    pub fn write_address(&mut self, addr: u8) -> nb::Result<(), void::Void> {
+        let usci = unsafe { USCI::steal() };
+        if usci.txifg_rd() {
+            usci.uctxaddr_set();
+            usci.tx_wr(addr);
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Send a break. If automatic baud rate detection is enabled, the break is followed by a 0x55
+    /// sync byte, forming a LIN break/sync field.
+    #[inline]
+    pub fn write_break(&mut self) -> nb::Result<(), void::Void> {
+        let usci = unsafe { USCI::steal() };
+        if usci.txifg_rd() {
+            usci.uctxbrk_set();
+            if usci.ucmode_rd() == UartUcmode::AutoBaud as u8 {
+                usci.tx_wr(0x55);
+            } else {
+                usci.tx_wr(0x00);
+            }
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
 }
 
-impl<USCI: SerialUsci> Write<u8> for Tx<USCI> {
+impl<USCI: SerialUsci, PIN> Write<u8> for Tx<USCI, PIN> {
     type Error = void::Void;
 
     /// Due to errata USCI42, UCTXCPTIFG will fire every time a byte is done transmitting,
@@ -476,9 +679,7 @@ impl<USCI: SerialUsci> Write<u8> for Tx<USCI> {
     /// Check if Tx interrupt flag is set. If so, write a byte into the Tx buffer. Otherwise block
     /// on the Tx flag.
     fn write(&mut self, data: u8) -> nb::Result<(), Self::Error> {
-        let usci = unsafe { USCI::steal() };
-        if usci.txifg_rd() {
-            usci.tx_wr(data);
+        if write_byte::<USCI>(data) {
             Ok(())
         } else {
             Err(nb::Error::WouldBlock)
@@ -486,12 +687,91 @@ impl<USCI: SerialUsci> Write<u8> for Tx<USCI> {
     }
 }
 
-impl<USCI: SerialUsci> embedded_hal::blocking::serial::write::Default<u8> for Tx<USCI> {}
+impl<USCI: SerialUsci, PIN> embedded_hal::blocking::serial::write::Default<u8> for Tx<USCI, PIN> {}
+
+impl<USCI: SerialUsci, PIN> core::fmt::Write for Tx<USCI, PIN> {
+    /// Blocks until the whole string has been written into the Tx buffer
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        write_all_blocking::<USCI>(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl<USCI: SerialUsci, PIN> Tx<USCI, Unpaired<PIN>> {
+    /// Put the eUSCI back into reset and return the PAC peripheral along with the GPIO pin passed
+    /// to `SerialConfig::tx_only()`. Any byte still being shifted out is cut off, so use `flush()`
+    /// or `is_idle()` first.
+    #[inline]
+    pub fn release_tx_only(self) -> (USCI, PIN) {
+        let usci = unsafe { USCI::steal() };
+        usci.ctl0_reset();
+        (usci, (self.0).0)
+    }
+}
+
+/// Serial receiver pin. `PIN` is the GPIO pin it was created from.
+pub struct Rx<USCI: SerialUsci, PIN> {
+    clk_freq: u32,
+    // Set when a break was reported by itself, so the next byte should be the sync byte
+    after_break: bool,
+    // An error hit by a multi-byte read after it had already stored some bytes, reported by the
+    // next read instead
+    pending_err: Option<RecvError>,
+    pin: PIN,
+    _usci: PhantomData<USCI>,
+}
+
+impl<USCI: SerialUsci, PIN> Rx<USCI, Unpaired<PIN>> {
+    /// Put the eUSCI back into reset and return the PAC peripheral along with the GPIO pin passed
+    /// to `SerialConfig::rx_only()`
+    #[inline]
+    pub fn release_rx_only(self) -> (USCI, PIN) {
+        let usci = unsafe { USCI::steal() };
+        usci.ctl0_reset();
+        (usci, self.pin.0)
+    }
+}
+
+impl<USCI: SerialUsci, PIN> Rx<USCI, PIN> {
+    #[inline(always)]
+    fn new(pin: PIN, clk_freq: u32) -> Self {
+        Rx {
+            clk_freq,
+            after_break: false,
+            pending_err: None,
+            pin,
+            _usci: PhantomData,
+        }
+    }
+
+    // Like `read_byte()`, but reports a deferred error first
+    #[inline(always)]
+    fn next_byte(&mut self) -> Option<Result<u8, RecvError>> {
+        match self.pending_err.take() {
+            Some(err) => Some(Err(err)),
+            None => read_byte::<USCI>(),
+        }
+    }
 
-/// Serial receiver pin
-pub struct Rx<USCI: SerialUsci>(PhantomData<USCI>);
+    // Fill `buf` with bytes that have already arrived without blocking. Stops at the first error,
+    // which is deferred to the next read so the bytes before it aren't lost.
+    fn read_available(&mut self, buf: &mut [u8]) -> usize {
+        let mut n = 0;
+        for b in buf {
+            match read_byte::<USCI>() {
+                Some(Ok(data)) => *b = data,
+                Some(Err(err)) => {
+                    self.pending_err = Some(err);
+                    break;
+                }
+                None => break,
+            }
+            n += 1;
+        }
+        n
+    }
 
-impl<USCI: SerialUsci> Rx<USCI> {
     /// Enable Rx interrupts, which fire when ready to read
     #[inline(always)]
     pub fn enable_rx_interrupts(&mut self) {
@@ -506,6 +786,46 @@ impl<USCI: SerialUsci> Rx<USCI> {
         usci.rxie_clear();
     }
 
+    /// Put the receiver to sleep, so that only address characters are received. Requires a
+    /// multiprocessor mode to be enabled with `SerialConfig::multiprocessor()`. The receiver
+    /// stays asleep after an address arrives, so call `wake()` to receive the data that follows.
+    #[inline]
+    pub fn sleep(&mut self) {
+        let usci = unsafe { USCI::steal() };
+        usci.set_ucdorm(true);
+    }
+
+    /// Wake the receiver so that all characters are received
+    #[inline]
+    pub fn wake(&mut self) {
+        let usci = unsafe { USCI::steal() };
+        usci.set_ucdorm(false);
+    }
+
+    /// Whether the character waiting in the Rx buffer is an address character. Only meaningful
+    /// in a multiprocessor mode.
+    #[inline]
+    pub fn is_address(&self) -> bool {
+        let usci = unsafe { USCI::steal() };
+        usci.statw_rd().ucaddr_ucidle()
+    }
+
+    /// Put the receiver to sleep and wait for an address character, discarding any data
+    /// characters already received. The receiver is left asleep, so call `wake()` if the address
+    /// matches this node.
+    pub fn read_address(&mut self) -> nb::Result<u8, RecvError> {
+        let usci = unsafe { USCI::steal() };
+        usci.set_ucdorm(true);
+        loop {
+            let is_address = usci.statw_rd().ucaddr_ucidle();
+            match read_byte::<USCI>() {
+                None => return Err(nb::Error::WouldBlock),
+                Some(res) if is_address => return res.map_err(nb::Error::Other),
+                Some(_) => (),
+            }
+        }
+    }
+
     /// Reads raw value from Rx buffer with no checks for validity
     /// # Safety
     /// May read duplicate data
@@ -526,6 +846,8 @@ impl<USCI: SerialUsci> Rx<USCI> {
 }
 
 /// Serial receive errors
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
 pub enum RecvError {
     /// Framing error
     Framing,
@@ -533,9 +855,116 @@ pub enum RecvError {
     Parity,
     /// Buffer overrun error. Contains the most recently read byte, which is still valid.
     Overrun(u8),
+    /// A break was received. Only reported if enabled with `SerialConfig::breaks()`.
+    Break,
+}
+
+/// Automatic baud rate detection errors
+#[derive(Clone, Copy, Debug)]
+pub enum AutoBaudError {
+    /// The break was longer than 22 bit times
+    BreakTimeout,
+    /// The sync field took too long to measure
+    SyncTimeout,
+    /// The byte following the break wasn't 0x55. Contains the received byte.
+    BadSync(u8),
+    /// The sync byte was corrupted
+    Recv(RecvError),
 }
 
-impl<USCI: SerialUsci> Read<u8> for Rx<USCI> {
+impl<USCI: SerialUsci, PIN> Rx<USCI, PIN> {
+    /// Whether a break (at least 10 bit times of zeros) has been received since the last byte was
+    /// read out of the Rx buffer. Reading a byte clears this flag.
+    #[inline]
+    pub fn break_detected(&self) -> bool {
+        let usci = unsafe { USCI::steal() };
+        usci.statw_rd().ucbrk()
+    }
+
+    /// Wait for a break followed by a 0x55 sync byte and return the baud rate measured from it.
+    /// The baud rate registers have already been updated by the hardware when this returns.
+    /// Bytes that aren't preceded by a break are discarded.
+    ///
+    /// Requires automatic baud rate detection to be enabled with `SerialConfig::auto_baud()`.
+    pub fn read_sync(&mut self) -> nb::Result<u32, AutoBaudError> {
+        let usci = unsafe { USCI::steal() };
+        if !usci.rxifg_rd() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        let statw = usci.statw_rd();
+        let data = usci.rx_rd();
+
+        if usci.ucbtoe_rd() || usci.ucstoe_rd() {
+            let err = if usci.ucbtoe_rd() {
+                AutoBaudError::BreakTimeout
+            } else {
+                AutoBaudError::SyncTimeout
+            };
+            usci.abctl_clear_timeouts();
+            return Err(nb::Error::Other(err));
+        }
+        // If breaks set RXIFG, the break arrives as its own character before the sync byte
+        if is_break(&statw, data) {
+            self.after_break = true;
+            return Err(nb::Error::WouldBlock);
+        }
+        if !statw.ucbrk() && !core::mem::take(&mut self.after_break) {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.after_break = false;
+        if statw.ucfe() {
+            return Err(nb::Error::Other(AutoBaudError::Recv(RecvError::Framing)));
+        }
+        if statw.ucpe() {
+            return Err(nb::Error::Other(AutoBaudError::Recv(RecvError::Parity)));
+        }
+        if data != 0x55 {
+            return Err(nb::Error::Other(AutoBaudError::BadSync(data)));
+        }
+        Ok(self.baudrate())
+    }
+
+    /// Current baud rate, calculated from the baud rate registers. After automatic baud rate
+    /// detection this is the measured rate.
+    #[inline]
+    pub fn baudrate(&self) -> u32 {
+        let usci = unsafe { USCI::steal() };
+        let br = usci.brw_rd() as u32;
+        let divisor = match usci.mctlw_rd() {
+            (true, brf) => br * 16 + brf as u32,
+            (false, _) => br,
+        };
+        self.clk_freq / divisor.max(1)
+    }
+
+    /// Reconfigure the baud rate registers for `baudrate`, which also applies to Tx. This turns
+    /// off automatic baud rate detection, so it can be used to lock in the rate reported by
+    /// `read_sync()` with the fractional modulation pattern that the hardware doesn't set.
+    ///
+    /// The eUSCI is briefly held in reset, so any byte being sent or received is lost.
+    pub fn set_baudrate(&mut self, baudrate: u32) {
+        let usci = unsafe { USCI::steal() };
+        let baud_config = calculate_baud_config(
+            self.clk_freq,
+            NonZeroU32::new(baudrate).unwrap_or(NonZeroU32::MIN),
+        );
+
+        // Setting UCSWRST clears the interrupt enables
+        let ie = usci.ie_rd();
+        usci.ctl0_set_rst();
+        usci.brw_settings(baud_config.br);
+        usci.mctlw_settings(baud_config.ucos16, baud_config.brs, baud_config.brf);
+        if usci.ucmode_rd() == UartUcmode::AutoBaud as u8 {
+            usci.abctl_settings(false);
+            usci.set_ucmode(UartUcmode::Uart);
+        }
+        usci.ctl0_clear_rst();
+        usci.ie_wr(ie);
+    }
+}
+
+impl<USCI: SerialUsci, PIN> Read<u8> for Rx<USCI, PIN> {
     type Error = RecvError;
 
     #[inline]
@@ -543,23 +972,569 @@ impl<USCI: SerialUsci> Read<u8> for Rx<USCI> {
     /// Otherwise block on the Rx interrupt flag. May return errors caused by data corruption or
     /// buffer overruns.
     fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        match self.next_byte() {
+            Some(res) => res.map_err(nb::Error::Other),
+            None => Err(nb::Error::WouldBlock),
+        }
+    }
+}
+
+// Returns `None` if no byte has been received
+#[inline(always)]
+fn read_byte<USCI: SerialUsci>() -> Option<Result<u8, RecvError>> {
+    let usci = unsafe { USCI::steal() };
+
+    if usci.rxifg_rd() {
+        let statw = usci.statw_rd();
+        let data = usci.rx_rd();
+
+        Some(if is_break(&statw, data) {
+            Err(RecvError::Break)
+        } else if statw.ucfe() {
+            Err(RecvError::Framing)
+        } else if statw.ucpe() {
+            Err(RecvError::Parity)
+        } else if statw.ucoe() {
+            Err(RecvError::Overrun(data))
+        } else {
+            Ok(data)
+        })
+    } else {
+        None
+    }
+}
+
+// UCBRK stays set until the Rx buffer is read, so it is also set on the first byte after a break
+// if the break itself didn't set RXIFG. The break character itself is all zeros, including the
+// stop bit.
+#[inline(always)]
+fn is_break<S: UartUcxStatw>(statw: &S, data: u8) -> bool {
+    statw.ucbrk() && statw.ucfe() && data == 0
+}
+
+// Returns false if the Tx buffer is still full
+#[inline(always)]
+fn write_byte<USCI: SerialUsci>(data: u8) -> bool {
+    let usci = unsafe { USCI::steal() };
+    if usci.txifg_rd() {
+        usci.tx_wr(data);
+        true
+    } else {
+        false
+    }
+}
+
+// Blocks on each byte until it's in the Tx buffer
+#[inline]
+pub(crate) fn write_all_blocking<USCI: SerialUsci>(bytes: &[u8]) {
+    for &b in bytes {
+        while !write_byte::<USCI>(b) {}
+    }
+}
+
+// Blocks until the last byte has been shifted out
+#[cfg(any(feature = "log", feature = "defmt"))]
+#[inline]
+pub(crate) fn flush_blocking<USCI: SerialUsci>() {
+    let usci = unsafe { USCI::steal() };
+    while !(usci.txifg_rd() && !usci.statw_rd().ucbusy()) {}
+}
+
+impl embedded_hal_nb::serial::Error for RecvError {
+    #[inline]
+    fn kind(&self) -> embedded_hal_nb::serial::ErrorKind {
+        use embedded_hal_nb::serial::ErrorKind;
+        match self {
+            RecvError::Framing => ErrorKind::FrameFormat,
+            RecvError::Parity => ErrorKind::Parity,
+            RecvError::Overrun(_) => ErrorKind::Overrun,
+            RecvError::Break => ErrorKind::Other,
+        }
+    }
+}
+
+impl embedded_io::Error for RecvError {
+    /// Overruns map to `Other`, since the byte in the error is valid but an earlier one was lost.
+    /// Framing errors, parity errors and breaks all mean the received byte itself is corrupt, so
+    /// they map to `InvalidData`.
+    #[inline]
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            RecvError::Overrun(_) => embedded_io::ErrorKind::Other,
+            RecvError::Framing | RecvError::Parity | RecvError::Break => {
+                embedded_io::ErrorKind::InvalidData
+            }
+        }
+    }
+}
+
+impl<USCI: SerialUsci, PIN> embedded_hal_nb::serial::ErrorType for Rx<USCI, PIN> {
+    type Error = RecvError;
+}
+
+impl<USCI: SerialUsci, PIN> embedded_hal_nb::serial::Read<u8> for Rx<USCI, PIN> {
+    #[inline]
+    fn read(&mut self) -> embedded_hal_nb::nb::Result<u8, Self::Error> {
+        match self.next_byte() {
+            Some(res) => res.map_err(embedded_hal_nb::nb::Error::Other),
+            None => Err(embedded_hal_nb::nb::Error::WouldBlock),
+        }
+    }
+}
+
+impl<USCI: SerialUsci, PIN> embedded_hal_nb::serial::ErrorType for Tx<USCI, PIN> {
+    type Error = core::convert::Infallible;
+}
+
+impl<USCI: SerialUsci, PIN> embedded_hal_nb::serial::Write<u8> for Tx<USCI, PIN> {
+    #[inline]
+    fn write(&mut self, data: u8) -> embedded_hal_nb::nb::Result<(), Self::Error> {
+        if write_byte::<USCI>(data) {
+            Ok(())
+        } else {
+            Err(embedded_hal_nb::nb::Error::WouldBlock)
+        }
+    }
+
+    /// Same as the embedded-hal 0.2 `flush()`: the Tx buffer will be empty, but the last byte may
+    /// still be shifting out.
+    #[inline]
+    fn flush(&mut self) -> embedded_hal_nb::nb::Result<(), Self::Error> {
+        let usci = unsafe { USCI::steal() };
+        if usci.txifg_rd() {
+            Ok(())
+        } else {
+            Err(embedded_hal_nb::nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<USCI: SerialUsci, PIN> embedded_io::ErrorType for Rx<USCI, PIN> {
+    type Error = RecvError;
+}
+
+impl<USCI: SerialUsci, PIN> embedded_io::Read for Rx<USCI, PIN> {
+    /// Blocks until at least one byte is received, then returns it along with any other bytes
+    /// that have already arrived. Since the eUSCI only buffers a single byte, this normally
+    /// returns one byte at a time.
+    ///
+    /// An error is only returned if no bytes were read. If a receive error occurs after some bytes
+    /// have been stored in `buf`, those bytes are returned and the error is reported by the next
+    /// read.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let Some((first, rest)) = buf.split_first_mut() else {
+            return Ok(0);
+        };
+        *first = loop {
+            if let Some(res) = self.next_byte() {
+                break res?;
+            }
+        };
+        Ok(1 + self.read_available(rest))
+    }
+}
+
+impl<USCI: SerialUsci, PIN> embedded_io::ReadReady for Rx<USCI, PIN> {
+    #[inline]
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
         let usci = unsafe { USCI::steal() };
+        Ok(self.pending_err.is_some() || usci.rxifg_rd())
+    }
+}
 
-        if usci.rxifg_rd() {
-            let statw = usci.statw_rd();
-            let data = usci.rx_rd();
+impl<USCI: SerialUsci, PIN> embedded_io::ErrorType for Tx<USCI, PIN> {
+    type Error = core::convert::Infallible;
+}
 
-            if statw.ucfe() {
-                Err(nb::Error::Other(RecvError::Framing))
-            } else if statw.ucpe() {
-                Err(nb::Error::Other(RecvError::Parity))
-            } else if statw.ucoe() {
-                Err(nb::Error::Other(RecvError::Overrun(data)))
+impl<USCI: SerialUsci, PIN> embedded_io::Write for Tx<USCI, PIN> {
+    /// Blocks until at least one byte can be written, then writes as many bytes as the eUSCI can
+    /// accept without blocking.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let Some((&first, rest)) = buf.split_first() else {
+            return Ok(0);
+        };
+        while !write_byte::<USCI>(first) {}
+        Ok(1 + rest.iter().take_while(|&&b| write_byte::<USCI>(b)).count())
+    }
+
+    /// Blocks until the last byte, including its stop bits, has been shifted out. Since UCBUSY is
+    /// also set while receiving, this may wait for an incoming byte to finish as well.
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        while !self.is_idle() {}
+        Ok(())
+    }
+}
+
+impl<USCI: SerialUsci, PIN> embedded_io::WriteReady for Tx<USCI, PIN> {
+    #[inline]
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        let usci = unsafe { USCI::steal() };
+        Ok(usci.txifg_rd())
+    }
+}
+
+// The eUSCI has no way to wake a task without an ISR, so these futures poll the interrupt flags and
+// immediately reschedule themselves. They work with any executor, but keep the CPU awake.
+#[cfg(feature = "embedded-io-async")]
+impl<USCI: SerialUsci, PIN> embedded_io_async::Read for Rx<USCI, PIN> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let Some((first, rest)) = buf.split_first_mut() else {
+            return Ok(0);
+        };
+        *first = core::future::poll_fn(|cx| match self.next_byte() {
+            Some(res) => core::task::Poll::Ready(res),
+            None => {
+                cx.waker().wake_by_ref();
+                core::task::Poll::Pending
+            }
+        })
+        .await?;
+        Ok(1 + self.read_available(rest))
+    }
+}
+
+#[cfg(feature = "embedded-io-async")]
+impl<USCI: SerialUsci, PIN> embedded_io_async::Write for Tx<USCI, PIN> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let Some((&first, rest)) = buf.split_first() else {
+            return Ok(0);
+        };
+        core::future::poll_fn(|cx| {
+            if write_byte::<USCI>(first) {
+                core::task::Poll::Ready(())
+            } else {
+                cx.waker().wake_by_ref();
+                core::task::Poll::Pending
+            }
+        })
+        .await;
+        Ok(1 + rest.iter().take_while(|&&b| write_byte::<USCI>(b)).count())
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        let usci = unsafe { USCI::steal() };
+        core::future::poll_fn(|cx| {
+            if usci.txifg_rd() && !usci.statw_rd().ucbusy() {
+                core::task::Poll::Ready(())
             } else {
-                Ok(data)
+                cx.waker().wake_by_ref();
+                core::task::Poll::Pending
+            }
+        })
+        .await;
+        Ok(())
+    }
+}
+
+/// Receiver that detects the end of a frame from line idle time, as used by Modbus RTU and many
+/// binary protocols
+///
+/// A sub-timer is restarted every time a byte arrives, and a frame-complete event is reported
+/// once it expires. The sub-timer's main timer must already be running with a threshold larger
+/// than the gap, e.g. by starting it with a count of `u16::MAX`.
+pub struct RxTimeout<USCI: SerialUsci, PIN, T: CapCmp<C>, C> {
+    rx: Rx<USCI, PIN>,
+    timer: SubTimer<T, C>,
+    gap_ticks: u16,
+    in_frame: bool,
+}
+
+impl<USCI: SerialUsci, PIN, T: TimerPeriph + CapCmp<C>, C> RxTimeout<USCI, PIN, T, C> {
+    /// Create a receiver that reports the end of a frame after `gap_ticks` timer ticks without a
+    /// byte. `char_times_to_ticks()` converts from character times.
+    #[inline]
+    pub fn new(rx: Rx<USCI, PIN>, timer: SubTimer<T, C>, gap_ticks: u16) -> Self {
+        RxTimeout {
+            rx,
+            timer,
+            gap_ticks,
+            in_frame: false,
+        }
+    }
+
+    /// Return the Rx pin and sub-timer
+    #[inline]
+    pub fn free(self) -> (Rx<USCI, PIN>, SubTimer<T, C>) {
+        (self.rx, self.timer)
+    }
+
+    /// Set the frame gap in timer ticks
+    #[inline]
+    pub fn set_gap_ticks(&mut self, gap_ticks: u16) {
+        self.gap_ticks = gap_ticks;
+    }
+
+    /// Whether a byte has been received since the last frame-complete event
+    #[inline]
+    pub fn in_frame(&self) -> bool {
+        self.in_frame
+    }
+
+    /// Return the next received byte, or `RxEvent::FrameComplete` once the line has been idle for
+    /// the frame gap after the last byte. Bytes received with errors also restart the gap.
+    pub fn read(&mut self) -> nb::Result<RxEvent, RecvError> {
+        if let Some(res) = read_byte::<USCI>() {
+            self.timer.set_timeout(self.gap_ticks);
+            self.in_frame = true;
+            return res.map(RxEvent::Byte).map_err(nb::Error::Other);
+        }
+        if self.in_frame && self.timer.wait().is_ok() {
+            self.in_frame = false;
+            return Ok(RxEvent::FrameComplete);
+        }
+        Err(nb::Error::WouldBlock)
+    }
+}
+
+#[cfg(feature = "modbus")]
+impl<USCI: SerialUsci, PIN, T: TimerPeriph + CapCmp<C>, C> crate::modbus::FrameReceiver
+    for RxTimeout<USCI, PIN, T, C>
+{
+    type Error = RecvError;
+
+    #[inline]
+    fn read(&mut self) -> nb::Result<RxEvent, RecvError> {
+        RxTimeout::read(self)
+    }
+}
+
+/// Indicates which flag caused an eUSCI UART interrupt to fire
+pub enum UartVector {
+    /// No pending interrupt
+    NoInterrupt,
+    /// A byte was received and is ready to be read
+    RxBufFull,
+    /// The transmit buffer is empty and ready for another byte
+    TxBufEmpty,
+    /// A start bit was received
+    StartBit,
+    /// A complete byte, including stop bits, was shifted out
+    TxComplete,
+}
+
+#[inline(always)]
+fn read_uart_iv<USCI: SerialUsci>(usci: &USCI) -> UartVector {
+    match usci.iv_rd() {
+        0 => UartVector::NoInterrupt,
+        2 => UartVector::RxBufFull,
+        4 => UartVector::TxBufEmpty,
+        6 => UartVector::StartBit,
+        8 => UartVector::TxComplete,
+        _ => unsafe { core::hint::unreachable_unchecked() },
+    }
+}
+
+struct RingBuffer<const N: usize> {
+    buf: [u8; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> RingBuffer<N> {
+    const fn new() -> Self {
+        RingBuffer {
+            buf: [0; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, byte: u8) -> bool {
+        if self.len == N {
+            return false;
+        }
+        self.buf[(self.head + self.len) % N] = byte;
+        self.len += 1;
+        true
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.buf[self.head];
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(byte)
+    }
+}
+
+/// Interrupt-driven serial port with `TX` and `RX` byte ring buffers
+///
+/// The buffers are stored inline, so placing the `BufferedSerial` in a `static` (e.g. a
+/// `static Mutex<RefCell<Option<..>>>`) allocates them statically. `handle_interrupt()` must be
+/// called from the eUSCI ISR, and interrupts must be enabled globally for data to move.
+/// `write()` and `read()` never block. Received bytes that don't fit in the Rx buffer are dropped
+/// and counted, as are bytes that don't fit in the Tx buffer when using `core::fmt::Write`.
+pub struct BufferedSerial<USCI: SerialUsci, TXPIN, RXPIN, const TX: usize, const RX: usize> {
+    tx: Tx<USCI, TXPIN>,
+    rx: Rx<USCI, RXPIN>,
+    tx_buf: RingBuffer<TX>,
+    rx_buf: RingBuffer<RX>,
+    tx_idle: bool,
+    tx_overflows: u16,
+    rx_overflows: u16,
+    rx_overruns: u16,
+    rx_errors: u16,
+}
+
+impl<USCI: SerialUsci, TXPIN, RXPIN, const TX: usize, const RX: usize>
+    BufferedSerial<USCI, TXPIN, RXPIN, TX, RX>
+{
+    /// Wrap a configured serial port. Enables the Rx interrupt.
+    #[inline]
+    pub fn new(tx: Tx<USCI, TXPIN>, mut rx: Rx<USCI, RXPIN>) -> Self {
+        rx.enable_rx_interrupts();
+        BufferedSerial {
+            tx,
+            rx,
+            tx_buf: RingBuffer::new(),
+            rx_buf: RingBuffer::new(),
+            tx_idle: true,
+            tx_overflows: 0,
+            rx_overflows: 0,
+            rx_overruns: 0,
+            rx_errors: 0,
+        }
+    }
+
+    /// Disable the serial interrupts and return the Tx and Rx pins. Any buffered data is discarded.
+    #[inline]
+    pub fn free(mut self) -> (Tx<USCI, TXPIN>, Rx<USCI, RXPIN>) {
+        self.tx.disable_tx_interrupts();
+        self.rx.disable_rx_interrupts();
+        (self.tx, self.rx)
+    }
+
+    /// Queue as many bytes from `data` as fit in the Tx buffer and return how many were queued.
+    pub fn write(&mut self, data: &[u8]) -> usize {
+        let queued = data
+            .iter()
+            .take_while(|&&byte| self.tx_buf.push(byte))
+            .count();
+
+        // The ISR stops once the buffer drains, so restart it by hand. Reading the interrupt
+        // vector cleared UCTXIFG, so the Tx interrupt won't fire again until a byte is written.
+        if self.tx_idle {
+            if let Some(byte) = self.tx_buf.pop() {
+                self.tx_idle = false;
+                let usci = unsafe { USCI::steal() };
+                usci.tx_wr(byte);
+                self.tx.enable_tx_interrupts();
             }
+        }
+        queued
+    }
+
+    /// Move received bytes into `buf` and return how many were read.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        buf.iter_mut()
+            .map_while(|b| self.rx_buf.pop().map(|byte| *b = byte))
+            .count()
+    }
+
+    /// Number of received bytes waiting to be read
+    #[inline]
+    pub fn rx_available(&self) -> usize {
+        self.rx_buf.len
+    }
+
+    /// Free space in the Tx buffer
+    #[inline]
+    pub fn tx_space(&self) -> usize {
+        TX - self.tx_buf.len
+    }
+
+    /// Whether every queued byte has been handed to the eUSCI. The final byte may still be
+    /// shifting out.
+    #[inline]
+    pub fn is_tx_idle(&self) -> bool {
+        self.tx_idle
+    }
+
+    /// Number of bytes dropped because the Tx buffer was full
+    #[inline]
+    pub fn tx_overflows(&self) -> u16 {
+        self.tx_overflows
+    }
+
+    /// Number of received bytes dropped because the Rx buffer was full
+    #[inline]
+    pub fn rx_overflows(&self) -> u16 {
+        self.rx_overflows
+    }
+
+    /// Number of hardware overruns, where a byte arrived before the previous one was read out of
+    /// the eUSCI
+    #[inline]
+    pub fn rx_overruns(&self) -> u16 {
+        self.rx_overruns
+    }
+
+    /// Number of received bytes dropped because of framing or parity errors
+    #[inline]
+    pub fn rx_errors(&self) -> u16 {
+        self.rx_errors
+    }
+
+    /// Reset all of the overflow and error counters to zero
+    #[inline]
+    pub fn clear_counters(&mut self) {
+        self.tx_overflows = 0;
+        self.rx_overflows = 0;
+        self.rx_overruns = 0;
+        self.rx_errors = 0;
+    }
+
+    /// Service the eUSCI interrupt. Should be called from the EUSCI_A0 or EUSCI_A1 ISR.
+    pub fn handle_interrupt(&mut self) {
+        let usci = unsafe { USCI::steal() };
+        loop {
+            match read_uart_iv(&usci) {
+                UartVector::NoInterrupt => break,
+                UartVector::RxBufFull => {
+                    let statw = usci.statw_rd();
+                    let data = usci.rx_rd();
+
+                    if statw.ucfe() || statw.ucpe() {
+                        self.rx_errors = self.rx_errors.wrapping_add(1);
+                        continue;
+                    }
+                    if statw.ucoe() {
+                        self.rx_overruns = self.rx_overruns.wrapping_add(1);
+                    }
+                    if !self.rx_buf.push(data) {
+                        self.rx_overflows = self.rx_overflows.wrapping_add(1);
+                    }
+                }
+                UartVector::TxBufEmpty => match self.tx_buf.pop() {
+                    Some(byte) => usci.tx_wr(byte),
+                    None => {
+                        self.tx_idle = true;
+                        usci.txie_clear();
+                    }
+                },
+                UartVector::StartBit | UartVector::TxComplete => (),
+            }
+        }
+    }
+}
+
+impl<USCI: SerialUsci, TXPIN, RXPIN, const TX: usize, const RX: usize> core::fmt::Write
+    for BufferedSerial<USCI, TXPIN, RXPIN, TX, RX>
+{
+    /// Returns an error if the string didn't fit in the Tx buffer. The excess bytes are dropped
+    /// and counted in `tx_overflows()`.
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let queued = self.write(s.as_bytes());
+        if queued == s.len() {
+            Ok(())
         } else {
-            Err(nb::Error::WouldBlock)
+            let dropped = (s.len() - queued) as u16;
+            self.tx_overflows = self.tx_overflows.wrapping_add(dropped);
+            Err(core::fmt::Error)
         }
     }
 }
@@ -0,0 +1,221 @@
+//! Baud rate settings and bit timing error
+//!
+//! These don't touch the hardware, so they are also compiled into the host tests.
+
+use core::num::NonZeroU32;
+
+/// Baud rate register settings
+#[derive(Clone, Copy, Debug)]
+pub struct BaudConfig {
+    /// UCBRx clock prescaler
+    pub br: u16,
+    /// UCBRSx second modulation stage pattern
+    pub brs: u8,
+    /// UCBRFx first modulation stage, only used in oversampling mode
+    pub brf: u8,
+    /// UCOS16 oversampling mode
+    pub ucos16: bool,
+}
+
+/// Register settings for `bps` from a `clk_freq` clock, following the baud rate setting
+/// algorithm from the user's guide. `const` so that baud rate errors can be checked at compile
+/// time.
+#[inline]
+pub const fn calculate_baud_config(clk_freq: u32, bps: NonZeroU32) -> BaudConfig {
+    let bps = bps.get();
+    // Ensure n stays within the 16 bit boundary
+    let n = clk_freq / bps;
+    let n = if n < 1 {
+        1
+    } else if n > 0xFFFF {
+        0xFFFF
+    } else {
+        n
+    };
+
+    let brs = lookup_brs(clk_freq, bps);
+
+    if (n >= 16) && (bps < u32::MAX / 16) {
+        //  div = bps * 16
+        let div = bps.saturating_mul(16);
+
+        // n / 16, but more precise
+        let br = (clk_freq / div) as u16;
+
+        // same as n % 16, but more precise
+        let brf = ((clk_freq % div) / bps) as u8;
+        BaudConfig {
+            ucos16: true,
+            br,
+            brf,
+            brs,
+        }
+    } else {
+        BaudConfig {
+            ucos16: false,
+            br: n as u16,
+            brf: 0,
+            brs,
+        }
+    }
+}
+
+#[inline(always)]
+const fn lookup_brs(clk_freq: u32, bps: u32) -> u8 {
+    // bps is between [1, 5_000_000] (datasheet max)
+    // clk_freq is between [0, 24_000_000] (datasheet max)
+
+    // modulo = clk_freq % bps => modulo is between [0, 4_999_999]
+    let modulo = clk_freq % bps;
+
+    // fraction = modulo * 10_000 / (bps), so within [0, ((bps-1) * 10_000) / bps].
+    // To prove upper bound we note `(bps-1)/bps` is largest when bps == 5_000_000:
+    // (4_999_999 * 10_000) / 5_000_000 = 49_999_990_000 (watch out for overflow!) / 5_000_000 = 9999.99... truncated to 9_999 because integer division
+    // So fraction is within [0, 9999]
+    let fraction_as_ten_thousandths = if modulo < u32::MAX/10_000 {
+        // Most accurate
+        ((modulo * 10_000) / bps) as u16
+    }
+    else { 
+        // Avoid overflow if modulo is large. Assume modulo < 5_000_000 from datasheet max
+        (((modulo * 500) / bps) * 20) as u16
+    };
+    
+    // See Table 22-4 from MSP430FR4xx and MSP430FR2xx family user's guide (Rev. I)
+    match fraction_as_ten_thousandths {
+        0..529     => 0x00,
+        529..715   => 0x01,
+        715..835   => 0x02,
+        835..1001  => 0x04,
+        1001..1252 => 0x08,
+        1252..1430 => 0x10,
+        1430..1670 => 0x20,
+        1670..2147 => 0x11,
+        2147..2224 => 0x21,
+        2224..2503 => 0x22,
+        2503..3000 => 0x44,
+        3000..3335 => 0x25,
+        3335..3575 => 0x49,
+        3575..3753 => 0x4A,
+        3753..4003 => 0x52,
+        4003..4286 => 0x92,
+        4286..4378 => 0x53,
+        4378..5002 => 0x55,
+        5002..5715 => 0xAA,
+        5715..6003 => 0x6B,
+        6003..6254 => 0xAD,
+        6254..6432 => 0xB5,
+        6432..6667 => 0xB6,
+        6667..7001 => 0xD6,
+        7001..7147 => 0xB7,
+        7147..7503 => 0xBB,
+        7503..7861 => 0xDD,
+        7861..8004 => 0xED,
+        8004..8333 => 0xEE,
+        8333..8464 => 0xBF,
+        8464..8572 => 0xDF,
+        8572..8751 => 0xEF,
+        8751..9004 => 0xF7,
+        9004..9170 => 0xFB,
+        9170..9288 => 0xFD,
+        9288..     => 0xFE,
+    }
+}
+
+/// Accuracy of the bit timing produced for a requested baud rate
+///
+/// Errors are in hundredths of a percent of one bit period. Each is the worst case over a whole
+/// frame, with a positive value meaning the bit edge or sample point is late.
+#[derive(Clone, Copy, Debug)]
+pub struct BaudRateError {
+    /// Average baud rate actually produced
+    pub achieved_baudrate: u32,
+    /// Worst-case transmit bit timing error
+    pub tx_error: i32,
+    /// Worst-case receive sampling error, including the uncertainty of half a clock cycle in
+    /// detecting the start bit
+    pub rx_error: i32,
+}
+
+impl BaudRateError {
+    /// Whether both the transmit and receive errors are within `max_error` hundredths of a
+    /// percent
+    #[inline]
+    pub const fn is_within(&self, max_error: u32) -> bool {
+        self.tx_error.unsigned_abs() <= max_error && self.rx_error.unsigned_abs() <= max_error
+    }
+}
+
+/// Calculate how accurately the UART can produce `baudrate` from a `clk_freq` clock, following
+/// the bit timing error calculation from the user's guide. Assumes 8 data bits, no parity and
+/// one stop bit. `SerialConfig::baud_rate_error()` accounts for the actual frame format.
+///
+/// This is a `const fn`, so a bad clock and baud rate pair can be rejected at compile time by
+/// asserting on `is_within()` inside a `const` item.
+#[inline]
+pub const fn baud_rate_error(clk_freq: u32, baudrate: u32) -> BaudRateError {
+    frame_baud_rate_error(clk_freq, baudrate, 10)
+}
+
+/// Like `baud_rate_error()`, for frames of `frame_bits` bits including the start and stop bits
+pub const fn frame_baud_rate_error(clk_freq: u32, baudrate: u32, frame_bits: u32) -> BaudRateError {
+    let bps = match NonZeroU32::new(baudrate) {
+        Some(bps) => bps,
+        None => NonZeroU32::MIN,
+    };
+    if clk_freq == 0 {
+        return BaudRateError {
+            achieved_baudrate: 0,
+            tx_error: i32::MAX,
+            rx_error: i32::MAX,
+        };
+    }
+    let config = calculate_baud_config(clk_freq, bps);
+    let baud = bps.get() as i64;
+    let clk = clk_freq as i64;
+
+    // Length of an unmodulated bit in clock cycles
+    let base = if config.ucos16 {
+        16 * config.br as i64 + config.brf as i64
+    } else {
+        config.br as i64
+    };
+
+    let mut tx_error: i64 = 0;
+    let mut rx_error: i64 = 0;
+    // Clock cycles from the start of the frame to the start of the current bit
+    let mut elapsed = 0;
+    let mut i = 0;
+    while i < frame_bits as i64 {
+        // Each bit of UCBRSx extends the corresponding bit by one clock cycle, starting with the
+        // MSB for the start bit
+        let bit = base + ((config.brs >> (7 - i % 8)) & 1) as i64;
+
+        // Bits are sampled in the middle, so work in half bits. The start bit edge is only
+        // detected to within half a clock cycle either way.
+        let mut sync = -1;
+        while sync <= 1 {
+            let sample = (2 * elapsed + bit + sync) * baud - (2 * i + 1) * clk;
+            let err = sample * 10_000 / (2 * clk);
+            if err.abs() > rx_error.abs() {
+                rx_error = err;
+            }
+            sync += 2;
+        }
+
+        elapsed += bit;
+        let err = (elapsed * baud - (i + 1) * clk) * 10_000 / clk;
+        if err.abs() > tx_error.abs() {
+            tx_error = err;
+        }
+        i += 1;
+    }
+
+    // The modulation pattern repeats every 8 bits
+    let pattern_len = 8 * base + config.brs.count_ones() as i64;
+    BaudRateError {
+        achieved_baudrate: (8 * clk / pattern_len) as u32,
+        tx_error: tx_error as i32,
+        rx_error: rx_error as i32,
+    }
+}
@@ -0,0 +1,31 @@
+//! Line-idle frame detection events and character timing
+//!
+//! This doesn't touch the hardware, so it is also compiled into the host tests.
+
+/// Convert a gap of `tenths` tenths of a character time into timer ticks, for characters of
+/// `bits_per_char` bits (including start, parity and stop bits) at `baudrate`. For example, the
+/// Modbus RTU frame gap of 3.5 characters is 35 tenths. Saturates at `u16::MAX`.
+#[inline]
+pub const fn char_times_to_ticks(
+    timer_freq: u32,
+    baudrate: u32,
+    bits_per_char: u32,
+    tenths: u32,
+) -> u16 {
+    let baudrate = if baudrate == 0 { 1 } else { baudrate };
+    let ticks = timer_freq as u64 * bits_per_char as u64 * tenths as u64 / (10 * baudrate as u64);
+    if ticks > u16::MAX as u64 {
+        u16::MAX
+    } else {
+        ticks as u16
+    }
+}
+
+/// Event reported by `RxTimeout`
+#[derive(Clone, Copy, Debug)]
+pub enum RxEvent {
+    /// A byte was received
+    Byte(u8),
+    /// The line has been idle for the configured gap since the last byte
+    FrameComplete,
+}
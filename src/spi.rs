@@ -1,14 +1,26 @@
 //! embedded_hal SPI implmentation
 use crate::hal::spi::{Mode, Phase, Polarity};
 use crate::{
-    clock::{Aclk, Smclk},
-    gpio::{Alternate1, Pin, Pin0, Pin1, Pin2, Pin3, Pin4, Pin5, Pin6, Pin7, P1, P4},
-    hw_traits::eusci::{EusciSPI, Ucmode, Ucssel, UcxSpiCtw0},
+    clock::{Aclk, Clock, Smclk},
+    gpio::{
+        Alternate1, Output, Pin, Pin0, Pin1, Pin2, Pin3, Pin4, Pin5, Pin6, Pin7, PinNum, PortNum,
+        P1, P4,
+    },
+    hw_traits::eusci::{EusciSPI, SpiStatw, Ucmode, Ucssel, UcxSpiCtw0},
 };
+use core::cell::{Cell, RefCell};
 use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ptr;
+use embedded_hal::digital::v2::OutputPin;
 use embedded_hal::spi::FullDuplex;
+use embedded_hal_1::delay::DelayNs;
+use embedded_hal_1::spi::{ErrorKind, ErrorType, Operation};
 use msp430fr2355 as pac;
 use nb::Error::WouldBlock;
+use void::ResultVoidExt;
+
+pub use crate::serial::{BitCount, BitOrder, Loopback};
 
 /// Marks a eUSCI capable of SPI communication (in this case, all euscis do)
 pub trait SpiUsci: EusciSPI {
@@ -134,6 +146,8 @@ pub struct ClockSet;
 pub struct SpiBusConfig<USCI: SpiUsci, STATE> {
     usci: USCI,
     prescaler: u16,
+    clk_freq: u32,
+    loopback: Loopback,
 
     // Register configs
     ctlw0: UcxSpiCtw0,
@@ -141,8 +155,11 @@ pub struct SpiBusConfig<USCI: SpiUsci, STATE> {
 }
 
 impl<USCI: SpiUsci> SpiBusConfig<USCI, NoClockSet> {
-    /// Create a new configuration for setting up a EUSCI peripheral in SPI mode
-    pub fn new(usci: USCI, mode: Mode, msb_first: bool) -> Self {
+    /// Create a new configuration for setting up a EUSCI peripheral in SPI mode.
+    ///
+    /// With `BitCount::SevenBits` each frame is 7 bits long. Words are still `u8`: the top bit of
+    /// each written byte is ignored, and the top bit of each read byte is always 0. See `SpiBus`.
+    pub fn new(usci: USCI, mode: Mode, order: BitOrder, cnt: BitCount) -> Self {
         let ctlw0 = UcxSpiCtw0 {
             ucckph: match mode.phase {
                 Phase::CaptureOnFirstTransition => true,
@@ -152,8 +169,8 @@ impl<USCI: SpiUsci> SpiBusConfig<USCI, NoClockSet> {
                 Polarity::IdleLow => false,
                 Polarity::IdleHigh => true,
             },
-            ucmsb: msb_first,
-            uc7bit: false,
+            ucmsb: order.to_bool(),
+            uc7bit: cnt.to_bool(),
             ucmst: true,
             ucsync: true,
             ucstem: true,
@@ -165,6 +182,8 @@ impl<USCI: SpiUsci> SpiBusConfig<USCI, NoClockSet> {
         SpiBusConfig {
             usci,
             prescaler: 0,
+            clk_freq: 0,
+            loopback: Loopback::NoLoop,
             ctlw0,
             _phantom: PhantomData,
         }
@@ -172,20 +191,57 @@ impl<USCI: SpiUsci> SpiBusConfig<USCI, NoClockSet> {
 
     /// Configures this peripheral to use smclk
     #[inline]
-    pub fn use_smclk(mut self, _smclk: &Smclk, clk_divisor: u16) -> SpiBusConfig<USCI, ClockSet>{
-        self.ctlw0.ucssel = Ucssel::Smclk;
-        self.prescaler = clk_divisor;
-        SpiBusConfig { usci: self.usci, prescaler: self.prescaler, ctlw0: self.ctlw0, _phantom: PhantomData }
+    pub fn use_smclk(self, smclk: &Smclk, clk_divisor: u16) -> SpiBusConfig<USCI, ClockSet>{
+        self.set_clock(Ucssel::Smclk, smclk.freq(), clk_divisor)
     }
 
     /// Configures this peripheral to use aclk
     #[inline]
-    pub fn use_aclk(mut self, _aclk: &Aclk, clk_divisor: u16) -> SpiBusConfig<USCI, ClockSet> {
-        self.ctlw0.ucssel = Ucssel::Aclk;
-        self.prescaler = clk_divisor;
-        SpiBusConfig { usci: self.usci, prescaler: self.prescaler, ctlw0: self.ctlw0, _phantom: PhantomData }
+    pub fn use_aclk(self, aclk: &Aclk, clk_divisor: u16) -> SpiBusConfig<USCI, ClockSet> {
+        self.set_clock(Ucssel::Aclk, aclk.freq() as u32, clk_divisor)
+    }
+
+    /// Configures this peripheral to use smclk, running the bus as close to `freq` Hz as possible
+    /// without exceeding it. The achieved rate can be read back with `SpiBus::frequency()`.
+    #[inline]
+    pub fn use_smclk_freq(self, smclk: &Smclk, freq: u32) -> SpiBusConfig<USCI, ClockSet> {
+        let clk_freq = smclk.freq();
+        self.set_clock(Ucssel::Smclk, clk_freq, prescaler_for(clk_freq, freq))
+    }
+
+    /// Configures this peripheral to use aclk, running the bus as close to `freq` Hz as possible
+    /// without exceeding it. The achieved rate can be read back with `SpiBus::frequency()`.
+    #[inline]
+    pub fn use_aclk_freq(self, aclk: &Aclk, freq: u32) -> SpiBusConfig<USCI, ClockSet> {
+        let clk_freq = aclk.freq() as u32;
+        self.set_clock(Ucssel::Aclk, clk_freq, prescaler_for(clk_freq, freq))
+    }
+
+    #[inline(always)]
+    fn set_clock(mut self, ucssel: Ucssel, clk_freq: u32, prescaler: u16) -> SpiBusConfig<USCI, ClockSet> {
+        self.ctlw0.ucssel = ucssel;
+        SpiBusConfig { usci: self.usci, prescaler, clk_freq, loopback: self.loopback, ctlw0: self.ctlw0, _phantom: PhantomData }
+    }
+}
+
+impl<USCI: SpiUsci, STATE> SpiBusConfig<USCI, STATE> {
+    /// Internally feed the transmitter output back into the receiver (UCLISTEN). Defaults to no loopback.
+    #[inline]
+    pub fn loopback(mut self, loopback: Loopback) -> Self {
+        self.loopback = loopback;
+        self
     }
 }
+
+/// Smallest prescaler that doesn't run the bus faster than `freq`. A prescaler of 0 acts as 1.
+#[inline(always)]
+fn prescaler_for(clk_freq: u32, freq: u32) -> u16 {
+    if freq == 0 {
+        return u16::MAX;
+    }
+    clk_freq.div_ceil(freq).clamp(1, u16::MAX as u32) as u16
+}
+
 #[allow(private_bounds)]
 impl<USCI: SpiUsci> SpiBusConfig<USCI, ClockSet> {
     /// Performs hardware configuration and creates an SPI bus. The STE pin is used as an automatically controlled chip select pin. Suitable for systems with only one slave device.
@@ -196,14 +252,42 @@ impl<USCI: SpiUsci> SpiBusConfig<USCI, ClockSet> {
         CLK: Into<USCI::SCLK>,
         STE: Into<USCI::STE>,
     >(
-        &mut self,
-        _miso: SO,
-        _mosi: SI,
-        _sclk: CLK,
-        _cs: STE,
-    ) -> SpiBus<USCI> {
-        self.configure_hw();
-        SpiBus(PhantomData)
+        self,
+        miso: SO,
+        mosi: SI,
+        sclk: CLK,
+        cs: STE,
+    ) -> SpiBus<USCI, (SO, SI, CLK, STE)> {
+        self.configure_hw((miso, mosi, sclk, cs))
+    }
+
+    /// Performs hardware configuration and creates an SPI bus for a system with multiple masters.
+    /// The STE pin is used as an input: while another master asserts it, this master's outputs are
+    /// disabled and any ongoing transfer fails with `SPIErr::FrameError`. You must configure and
+    /// control any chip select pins yourself.
+    ///
+    /// The conflict flag raises no interrupt, so a background transfer from `start_transfer()`
+    /// only notices a conflict when it's polled or the next eUSCI interrupt fires.
+    #[inline(always)]
+    pub fn configure_with_multi_master<
+        SO: Into<USCI::MISO>,
+        SI: Into<USCI::MOSI>,
+        CLK: Into<USCI::SCLK>,
+        STE: Into<USCI::STE>,
+    >(
+        mut self,
+        miso: SO,
+        mosi: SI,
+        sclk: CLK,
+        ste: STE,
+        ste_polarity: StePolarity,
+    ) -> SpiBus<USCI, (SO, SI, CLK, STE)> {
+        self.ctlw0.ucstem = false;
+        self.ctlw0.ucmode = match ste_polarity {
+            StePolarity::ActiveHigh => Ucmode::FourPinSPI1,
+            StePolarity::ActiveLow => Ucmode::FourPinSPI0,
+        };
+        self.configure_hw((miso, mosi, sclk, ste))
     }
 
     /// Performs hardware configuration and creates an SPI bus. You must configure and control any chip select pins yourself. Suitable for systems with multiple slave devices. 
@@ -213,35 +297,90 @@ impl<USCI: SpiUsci> SpiBusConfig<USCI, ClockSet> {
         SI: Into<USCI::MOSI>,
         CLK: Into<USCI::SCLK>,
     >(
-        &mut self,
-        _miso: SO,
-        _mosi: SI,
-        _sclk: CLK
-    ) -> SpiBus<USCI> {
+        mut self,
+        miso: SO,
+        mosi: SI,
+        sclk: CLK
+    ) -> SpiBus<USCI, (SO, SI, CLK)> {
         self.ctlw0.ucmode = Ucmode::ThreePinSPI;
-        self.configure_hw();
-        SpiBus(PhantomData)
+        self.configure_hw((miso, mosi, sclk))
     }
 
     #[inline]
-    fn configure_hw(&self) {
+    fn configure_hw<PINS>(self, pins: PINS) -> SpiBus<USCI, PINS> {
         self.usci.ctw0_set_rst();
 
         self.usci.ctw0_wr(&self.ctlw0);
         self.usci.brw_wr(self.prescaler);
-        self.usci.uclisten_clear();
+        match self.loopback {
+            Loopback::NoLoop => self.usci.uclisten_clear(),
+            Loopback::Loopback => self.usci.uclisten_set(),
+        }
 
         self.usci.ctw0_clear_rst();
 
         self.usci.clear_transmit_interrupt();
         self.usci.clear_receive_interrupt();
+
+        SpiBus {
+            prescaler: self.prescaler,
+            clk_freq: self.clk_freq,
+            pins,
+            _usci: PhantomData,
+        }
     }
 }
 
+/// Level of the STE pin that indicates another master is active, used in multi-master mode
+#[derive(Clone, Copy)]
+pub enum StePolarity {
+    /// Another master is active while STE is high
+    ActiveHigh,
+    /// Another master is active while STE is low
+    ActiveLow,
+}
+
+/// Snapshot of the SPI status flags
+#[derive(Clone, Copy, Debug)]
+pub struct SpiStatus {
+    /// A byte is currently being transmitted or received
+    pub busy: bool,
+    /// The receive buffer was overwritten before it was read
+    pub overrun: bool,
+    /// Another master was active on the bus. Only set in multi-master mode.
+    pub framing_error: bool,
+}
+
 /// Represents a group of pins configured for SPI communication
-pub struct SpiBus<USCI: SpiUsci>(PhantomData<USCI>);
+///
+/// Words are always `u8`, including with `BitCount::SevenBits`. In 7-bit mode only the low 7 bits
+/// of each written word are sent and bit 7 is ignored, so `0x80 | x` goes out the same as `x`.
+/// Every received word has bit 7 cleared. This applies to `FullDuplex`, the embedded-hal 1.0
+/// `SpiBus` and `SpiDevice` traits and background transfers alike. The `0xFF` filler sent while
+/// reading goes out as `0x7F`.
+///
+/// `PINS` holds the GPIO pins passed to the configure method, as a `(MISO, MOSI, SCLK)` tuple with
+/// software CS or a `(MISO, MOSI, SCLK, STE)` tuple otherwise.
+pub struct SpiBus<USCI: SpiUsci, PINS> {
+    prescaler: u16,
+    clk_freq: u32,
+    pins: PINS,
+    _usci: PhantomData<USCI>,
+}
+
+impl<USCI: SpiUsci, PINS> SpiBus<USCI, PINS> {
+    /// Wait for any ongoing transfer to finish, put the eUSCI back into reset and return the PAC
+    /// peripheral along with the GPIO pins, exactly as they were passed to the configure method.
+    /// The pins stay in alternate function 1, so they can be passed straight to another eUSCI
+    /// configuration, such as `SerialConfig` on an eUSCI_A.
+    #[inline]
+    pub fn release(self) -> (USCI, PINS) {
+        let usci = unsafe { USCI::steal() };
+        while usci.busy_flag() {}
+        usci.ctw0_set_rst();
+        (usci, self.pins)
+    }
 
-impl<USCI: SpiUsci> SpiBus<USCI> {
     /// Enable Rx interrupts, which fire when a byte is ready to be read
     #[inline(always)]
     pub fn set_rx_interrupt(&mut self) {
@@ -291,10 +430,372 @@ impl<USCI: SpiUsci> SpiBus<USCI> {
     #[inline(always)]
     /// Change the SPI mode
     pub fn change_mode(&mut self, mode: Mode) {
+        self.reconfigure(|usci| usci.set_spi_mode(mode));
+    }
+
+    /// Change the bus clock to be as close to `freq` Hz as possible without exceeding it, returning
+    /// the achieved frequency. Blocks until any ongoing transfer has finished.
+    #[inline]
+    pub fn set_frequency(&mut self, freq: u32) -> u32 {
+        let prescaler = prescaler_for(self.clk_freq, freq);
+        self.prescaler = prescaler;
+        self.reconfigure(|usci| usci.brw_wr(prescaler));
+        self.frequency()
+    }
+
+    /// Change the order in which bits are shifted out. Blocks until any ongoing transfer has finished.
+    #[inline]
+    pub fn set_bit_order(&mut self, bit_order: BitOrder) {
+        self.reconfigure(|usci| usci.set_ucmsb(bit_order.to_bool()));
+    }
+
+    // Apply all of a device's settings with a single trip through reset
+    #[inline]
+    fn apply_device_config(&mut self, config: &SpiDeviceConfig) {
+        let prescaler = prescaler_for(self.clk_freq, config.frequency);
+        self.prescaler = prescaler;
+        self.reconfigure(|usci| {
+            usci.set_spi_mode(config.mode);
+            usci.set_ucmsb(config.bit_order.to_bool());
+            usci.brw_wr(prescaler);
+        });
+    }
+
+    /// Internally feed the transmitter output back into the receiver, so every byte sent is also
+    /// the byte received. The clock and MOSI pins are still driven, so any devices on the bus
+    /// should be deselected. Blocks until any ongoing transfer has finished.
+    #[inline]
+    pub fn enable_loopback(&mut self) {
+        self.reconfigure(|usci| usci.uclisten_set());
+    }
+
+    /// Disable loopback mode. Blocks until any ongoing transfer has finished.
+    #[inline]
+    pub fn disable_loopback(&mut self) {
+        self.reconfigure(|usci| usci.uclisten_clear());
+    }
+
+    /// Read the current status flags. Reading does not clear any of them.
+    #[inline]
+    pub fn status(&self) -> SpiStatus {
+        let usci = unsafe { USCI::steal() };
+        let statw = usci.statw_rd();
+        SpiStatus {
+            busy: statw.ucbusy(),
+            overrun: statw.ucoe(),
+            framing_error: statw.ucfe(),
+        }
+    }
+
+    /// Whether loopback mode is currently enabled
+    #[inline]
+    pub fn is_loopback(&self) -> bool {
+        let usci = unsafe { USCI::steal() };
+        usci.statw_rd().uclisten()
+    }
+
+    /// Check that the eUSCI works by sending `pattern` in loopback mode and verifying each byte is
+    /// received back unchanged. Loopback mode is restored to its previous setting afterwards.
+    ///
+    /// When using 7-bit characters the top bit of every byte in `pattern` must be clear.
+    /// `SELF_TEST_PATTERN` is a reasonable default for 8-bit characters.
+    pub fn self_test(&mut self, pattern: &[u8]) -> Result<(), SelfTestError> {
+        let was_loopback = self.is_loopback();
+        if !was_loopback {
+            self.enable_loopback();
+        }
+
         let usci = unsafe { USCI::steal() };
+        Self::clear_stale_rx(&usci);
+        let mut res = Ok(());
+        for (index, &sent) in pattern.iter().enumerate() {
+            match Self::send_blocking(&usci, sent).and_then(|_| Self::recv_blocking(&usci)) {
+                Ok(received) if received == sent => (),
+                Ok(received) => {
+                    res = Err(SelfTestError::Mismatch { index, sent, received });
+                    break;
+                }
+                Err(err) => {
+                    res = Err(SelfTestError::Bus(err));
+                    break;
+                }
+            }
+        }
+
+        if !was_loopback {
+            self.disable_loopback();
+        }
+        res
+    }
+
+    /// The actual frequency of the bus clock in Hz
+    #[inline]
+    pub fn frequency(&self) -> u32 {
+        self.clk_freq / self.prescaler.max(1) as u32
+    }
+
+    // Putting the eUSCI into reset clears the interrupt enable bits, so they are saved and restored
+    // around `f`. We wait for the bus to go idle first so the byte in flight isn't cut short.
+    #[inline(always)]
+    fn reconfigure(&mut self, f: impl FnOnce(&USCI)) {
+        let usci = unsafe { USCI::steal() };
+        while usci.busy_flag() {}
+        let ie = usci.ie_rd();
         usci.ctw0_set_rst();
-        usci.set_spi_mode(mode);
+        f(&usci);
         usci.ctw0_clear_rst();
+        usci.ie_wr(ie);
+    }
+}
+
+/// Default pattern for `SpiBus::self_test()`: all zeroes, all ones, alternating bits and a walking one
+pub const SELF_TEST_PATTERN: [u8; 12] =
+    [0x00, 0xFF, 0xAA, 0x55, 0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80];
+
+/// Errors returned by `SpiBus::self_test()`
+#[derive(Clone, Copy, Debug)]
+pub enum SelfTestError {
+    /// The bus reported an error while sending the pattern
+    Bus(SPIErr),
+    /// A byte was not received back unchanged
+    Mismatch {
+        /// Position of the byte in the pattern
+        index: usize,
+        /// Byte that was sent
+        sent: u8,
+        /// Byte that was received
+        received: u8,
+    },
+}
+
+/// Indicates which flag caused an eUSCI SPI interrupt to fire
+pub enum SpiVector {
+    /// No pending interrupt
+    NoInterrupt,
+    /// A byte was received and is ready to be read
+    RxBufFull,
+    /// The transmit buffer is empty and ready for another byte
+    TxBufEmpty,
+}
+
+#[inline(always)]
+fn read_spi_iv<USCI: SpiUsci>(usci: &USCI) -> SpiVector {
+    match usci.iv_rd() {
+        0 => SpiVector::NoInterrupt,
+        2 => SpiVector::RxBufFull,
+        4 => SpiVector::TxBufEmpty,
+        _ => unsafe { core::hint::unreachable_unchecked() },
+    }
+}
+
+impl<USCI: SpiUsci, PINS> SpiBus<USCI, PINS> {
+    /// When called inside an ISR, returns the highest priority pending SPI interrupt flag.
+    /// Automatically clears the same flag.
+    #[inline]
+    pub fn interrupt_vector(&mut self) -> SpiVector {
+        let usci = unsafe { USCI::steal() };
+        read_spi_iv(&usci)
+    }
+
+    /// Start a full duplex transfer that runs in the background, serviced from the eUSCI interrupt.
+    ///
+    /// `len = max(read.len(), write.len())` bytes are clocked out. Bytes past the end of `write`
+    /// are sent as `0xFF`, and received bytes past the end of `read` are discarded.
+    ///
+    /// Rx and Tx interrupts are enabled by this method. The returned `SpiTransfer` should be moved
+    /// somewhere the ISR can access it (e.g. a `static Mutex<RefCell<Option<..>>>`) and
+    /// `SpiTransfer::handle_interrupt()` called from the ISR. Interrupts must also be enabled
+    /// globally for the transfer to progress.
+    #[inline]
+    pub fn start_transfer<'a>(self, read: &'a mut [u8], write: &'a [u8]) -> SpiTransfer<'a, USCI, PINS> {
+        let usci = unsafe { USCI::steal() };
+        Self::clear_stale_rx(&usci);
+
+        let len = read.len().max(write.len());
+        let state = if len == 0 {
+            TransferState::Done
+        } else {
+            usci.set_receive_interrupt();
+            usci.set_transmit_interrupt();
+            TransferState::InProgress
+        };
+
+        SpiTransfer {
+            bus: self,
+            read,
+            write,
+            len,
+            sent: 0,
+            received: 0,
+            state: Cell::new(state),
+            callback: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum TransferState {
+    InProgress,
+    Done,
+    Failed(SPIErr),
+}
+
+/// An SPI transfer running in the background, started by `SpiBus::start_transfer()`.
+pub struct SpiTransfer<'a, USCI: SpiUsci, PINS> {
+    bus: SpiBus<USCI, PINS>,
+    read: &'a mut [u8],
+    write: &'a [u8],
+    len: usize,
+    sent: usize,
+    received: usize,
+    // In a `Cell` so that polling from `&self` can fail the transfer on a bus conflict
+    state: Cell<TransferState>,
+    callback: Option<fn(Result<(), SPIErr>)>,
+}
+
+impl<'a, USCI: SpiUsci, PINS> SpiTransfer<'a, USCI, PINS> {
+    /// Set a function to be called when the transfer completes or fails.
+    /// The callback runs in the interrupt context.
+    #[inline]
+    pub fn on_complete(mut self, callback: fn(Result<(), SPIErr>)) -> Self {
+        self.callback = Some(callback);
+        self
+    }
+
+    /// Service the transfer. Call this from the eUSCI ISR.
+    ///
+    /// Reads the interrupt vector, so it clears the highest priority pending flag.
+    pub fn handle_interrupt(&mut self) {
+        let usci = unsafe { USCI::steal() };
+        let vector = read_spi_iv(&usci);
+        if self.check_conflict(&usci) {
+            return;
+        }
+        match vector {
+            SpiVector::RxBufFull => {
+                if usci.overrun_flag() {
+                    self.finish(&usci, TransferState::Failed(SPIErr::OverrunError(usci.rxbuf_rd())));
+                    return;
+                }
+                let byte = usci.rxbuf_rd();
+                if let Some(r) = self.read.get_mut(self.received) {
+                    *r = byte;
+                }
+                self.received += 1;
+
+                if self.received == self.len {
+                    self.finish(&usci, TransferState::Done);
+                } else if self.sent < self.len {
+                    usci.set_transmit_interrupt();
+                }
+            }
+            SpiVector::TxBufEmpty => {
+                // Keep at most one byte queued behind the one being shifted out, otherwise the
+                // receive buffer could be overwritten before the Rx interrupt is serviced.
+                if self.sent < self.len && self.sent < self.received + 2 {
+                    usci.txbuf_wr(self.write.get(self.sent).copied().unwrap_or(DUMMY_BYTE));
+                    self.sent += 1;
+                }
+                if self.sent == self.len || self.sent == self.received + 2 {
+                    usci.clear_transmit_interrupt();
+                }
+            }
+            SpiVector::NoInterrupt => (),
+        }
+    }
+
+    #[inline(always)]
+    fn finish(&self, usci: &USCI, state: TransferState) {
+        usci.clear_transmit_interrupt();
+        usci.clear_receive_interrupt();
+        self.state.set(state);
+        if let Some(callback) = self.callback {
+            callback(self.result());
+        }
+    }
+
+    // UCFE doesn't raise an interrupt, and a bus conflict stops the transfer, so the Rx/Tx
+    // interrupts may never fire again. Check for it from the ISR and whenever the transfer is
+    // polled, failing the transfer if it's set. Returns whether the transfer failed.
+    #[inline(always)]
+    fn check_conflict(&self, usci: &USCI) -> bool {
+        if let TransferState::InProgress = self.state.get() {
+            if usci.framing_flag() {
+                usci.clear_framing_flag();
+                self.finish(usci, TransferState::Failed(SPIErr::FrameError));
+                return true;
+            }
+        }
+        false
+    }
+
+    #[inline(always)]
+    fn result(&self) -> Result<(), SPIErr> {
+        match self.state.get() {
+            TransferState::Failed(err) => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the transfer has finished, either successfully or with an error. In multi-master
+    /// mode this also checks for a bus conflict, which fails the transfer.
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        let usci = unsafe { USCI::steal() };
+        self.check_conflict(&usci);
+        !matches!(self.state.get(), TransferState::InProgress)
+    }
+
+    /// Check the result of the transfer, returning `WouldBlock` if it's still in progress. In
+    /// multi-master mode this also checks for a bus conflict, which fails the transfer with
+    /// `SPIErr::FrameError`. A conflict raises no interrupt, so poll the transfer (or call
+    /// `is_done()`) to be sure it's noticed.
+    #[inline]
+    pub fn poll(&self) -> nb::Result<(), SPIErr> {
+        let usci = unsafe { USCI::steal() };
+        self.check_conflict(&usci);
+        match self.state.get() {
+            TransferState::InProgress => Err(WouldBlock),
+            TransferState::Done => Ok(()),
+            TransferState::Failed(err) => Err(nb::Error::Other(err)),
+        }
+    }
+
+    /// Number of bytes received so far
+    #[inline]
+    pub fn bytes_received(&self) -> usize {
+        self.received
+    }
+
+    /// Return the SPI bus. If the transfer is still in progress it is aborted after the current
+    /// byte finishes shifting. Rx and Tx interrupts are disabled.
+    #[inline]
+    pub fn free(self) -> SpiBus<USCI, PINS> {
+        let this = ManuallyDrop::new(self);
+        this.abort();
+        // `this` is never dropped, so the bus is moved out exactly once. The other fields are
+        // references, which need no cleanup.
+        unsafe { ptr::read(&this.bus) }
+    }
+
+    // Stop servicing the transfer: disable the interrupts that drive it, let the byte being
+    // shifted finish and discard whatever it received
+    #[inline(always)]
+    fn abort(&self) {
+        let usci = unsafe { USCI::steal() };
+        usci.clear_transmit_interrupt();
+        usci.clear_receive_interrupt();
+        SpiBus::<USCI, PINS>::clear_stale_rx(&usci);
+    }
+}
+
+/// Dropping a transfer that's still in progress aborts it the same way as `free()`, so the eUSCI
+/// interrupts never fire for a transfer whose buffers are gone. A transfer leaked with
+/// `core::mem::forget()` is not aborted and leaves the interrupts enabled.
+impl<'a, USCI: SpiUsci, PINS> Drop for SpiTransfer<'a, USCI, PINS> {
+    #[inline]
+    fn drop(&mut self) {
+        self.abort();
     }
 }
 
@@ -304,14 +805,22 @@ impl<USCI: SpiUsci> SpiBus<USCI> {
 pub enum SPIErr {
     /// Data in the recieve buffer was overwritten before it was read. The contained data is the new contents of the recieve buffer.
     OverrunError(u8),
-    // In future the framing error bit UCFE may appear here. Right now it's unimplemented.
+    /// Another master was active on the bus (UCFE). Only reported when configured with `configure_with_multi_master()`.
+    /// Background transfers report it from `SpiTransfer::poll()`, `SpiTransfer::is_done()` or the next eUSCI interrupt,
+    /// since the flag itself raises no interrupt.
+    FrameError,
+    /// A `SharedSpiDevice` found the shared bus already in use by another device, e.g. when used
+    /// from an interrupt that fired during another device's transaction
+    BusBusy,
 }
 
-impl<USCI: SpiUsci> FullDuplex<u8> for SpiBus<USCI> {
+/// In 7-bit mode bit 7 of sent words is ignored and bit 7 of read words is 0. See `SpiBus`.
+impl<USCI: SpiUsci, PINS> FullDuplex<u8> for SpiBus<USCI, PINS> {
     type Error = SPIErr;
     fn read(&mut self) -> nb::Result<u8, Self::Error> {
         let usci = unsafe { USCI::steal() };
-        
+        Self::check_framing(&usci)?;
+
         if usci.receive_flag() {
             if usci.overrun_flag() {
                 Err(nb::Error::Other(SPIErr::OverrunError(usci.rxbuf_rd())))
@@ -326,6 +835,8 @@ impl<USCI: SpiUsci> FullDuplex<u8> for SpiBus<USCI> {
 
     fn send(&mut self, word: u8) -> nb::Result<(), Self::Error> {
         let usci = unsafe { USCI::steal() };
+        Self::check_framing(&usci)?;
+
         if usci.transmit_flag() {
             usci.txbuf_wr(word);
             Ok(())
@@ -336,5 +847,316 @@ impl<USCI: SpiUsci> FullDuplex<u8> for SpiBus<USCI> {
 }
 
 // Implementing FullDuplex above gets us a blocking write and transfer implementation for free
-impl<USCI: SpiUsci> embedded_hal::blocking::spi::write::Default<u8> for SpiBus<USCI> {}
-impl<USCI: SpiUsci> embedded_hal::blocking::spi::transfer::Default<u8> for SpiBus<USCI> {}
+impl<USCI: SpiUsci, PINS> embedded_hal::blocking::spi::write::Default<u8> for SpiBus<USCI, PINS> {}
+impl<USCI: SpiUsci, PINS> embedded_hal::blocking::spi::transfer::Default<u8> for SpiBus<USCI, PINS> {}
+
+impl embedded_hal_1::spi::Error for SPIErr {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            SPIErr::OverrunError(_) => ErrorKind::Overrun,
+            SPIErr::FrameError => ErrorKind::ModeFault,
+            SPIErr::BusBusy => ErrorKind::Other,
+        }
+    }
+}
+
+/// Byte sent on MOSI when only reading from the bus
+const DUMMY_BYTE: u8 = 0xFF;
+
+impl<USCI: SpiUsci, PINS> SpiBus<USCI, PINS> {
+    /// Wait for the eUSCI to finish shifting, then discard any leftover byte in the Rx buffer so
+    /// a new transaction starts with a clean RXIFG and overrun flag.
+    #[inline(always)]
+    fn clear_stale_rx(usci: &USCI) {
+        while usci.busy_flag() {}
+        if usci.receive_flag() {
+            usci.rxbuf_rd();
+        }
+    }
+
+    // A bus conflict in multi-master mode stops the transfer, so the Rx/Tx flags may never be set.
+    // Check for it while waiting on them.
+    #[inline(always)]
+    fn check_framing(usci: &USCI) -> Result<(), SPIErr> {
+        if usci.framing_flag() {
+            usci.clear_framing_flag();
+            Err(SPIErr::FrameError)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline(always)]
+    fn recv_blocking(usci: &USCI) -> Result<u8, SPIErr> {
+        while !usci.receive_flag() {
+            Self::check_framing(usci)?;
+        }
+        if usci.overrun_flag() {
+            Err(SPIErr::OverrunError(usci.rxbuf_rd()))
+        } else {
+            Ok(usci.rxbuf_rd())
+        }
+    }
+
+    #[inline(always)]
+    fn send_blocking(usci: &USCI, word: u8) -> Result<(), SPIErr> {
+        while !usci.transmit_flag() {
+            Self::check_framing(usci)?;
+        }
+        usci.txbuf_wr(word);
+        Ok(())
+    }
+
+    // Pipelined transfer: the next byte is loaded into TXBUF while the previous one is still being
+    // shifted out, so the bus never idles between bytes. Bytes past the end of `write` are sent as
+    // `DUMMY_BYTE`, and bytes past the end of `read` are discarded.
+    fn transfer_pipelined(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), SPIErr> {
+        let len = read.len().max(write.len());
+        if len == 0 {
+            return Ok(());
+        }
+        let usci = unsafe { USCI::steal() };
+        Self::clear_stale_rx(&usci);
+
+        Self::send_blocking(&usci, write.first().copied().unwrap_or(DUMMY_BYTE))?;
+        for i in 1..len {
+            Self::send_blocking(&usci, write.get(i).copied().unwrap_or(DUMMY_BYTE))?;
+            let byte = Self::recv_blocking(&usci)?;
+            if let Some(r) = read.get_mut(i - 1) {
+                *r = byte;
+            }
+        }
+        let byte = Self::recv_blocking(&usci)?;
+        if let Some(r) = read.get_mut(len - 1) {
+            *r = byte;
+        }
+        Ok(())
+    }
+}
+
+impl<USCI: SpiUsci, PINS> ErrorType for SpiBus<USCI, PINS> {
+    type Error = SPIErr;
+}
+
+/// In 7-bit mode bit 7 of written words is ignored and bit 7 of read words is 0. See `SpiBus`.
+impl<USCI: SpiUsci, PINS> embedded_hal_1::spi::SpiBus<u8> for SpiBus<USCI, PINS> {
+    /// Reads `words` from the bus, sending `0xFF` for each byte read.
+    #[inline]
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.transfer_pipelined(words, &[])
+    }
+
+    /// Writes `words` to the bus. Received bytes are discarded, so overruns are not reported.
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let usci = unsafe { USCI::steal() };
+        for &word in words {
+            Self::send_blocking(&usci, word)?;
+        }
+        Self::clear_stale_rx(&usci);
+        Ok(())
+    }
+
+    #[inline]
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        self.transfer_pipelined(read, write)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        let len = words.len();
+        if len == 0 {
+            return Ok(());
+        }
+        let usci = unsafe { USCI::steal() };
+        Self::clear_stale_rx(&usci);
+
+        Self::send_blocking(&usci, words[0])?;
+        for i in 1..len {
+            Self::send_blocking(&usci, words[i])?;
+            words[i - 1] = Self::recv_blocking(&usci)?;
+        }
+        words[len - 1] = Self::recv_blocking(&usci)?;
+        Ok(())
+    }
+
+    /// Blocks until the last byte has been completely shifted out.
+    #[inline]
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        let usci = unsafe { USCI::steal() };
+        while usci.busy_flag() {}
+        Ok(())
+    }
+}
+
+/// An SPI device with exclusive access to an SPI bus, using a GPIO pin as chip select.
+///
+/// Implements the embedded-hal 1.0 `SpiDevice` trait. The chip select pin is asserted (driven low)
+/// for the duration of each transaction and deasserted afterwards, even if the transaction fails.
+pub struct SpiDevice<USCI: SpiUsci, PINS, PORT: PortNum, PIN: PinNum, DELAY> {
+    bus: SpiBus<USCI, PINS>,
+    cs: Pin<PORT, PIN, Output>,
+    delay: DELAY,
+}
+
+impl<USCI: SpiUsci, PINS, PORT: PortNum, PIN: PinNum, DELAY: DelayNs> SpiDevice<USCI, PINS, PORT, PIN, DELAY> {
+    /// Create a new SPI device from a bus, a chip select pin and a delay provider.
+    /// The chip select pin is deasserted (driven high).
+    #[inline]
+    pub fn new(bus: SpiBus<USCI, PINS>, mut cs: Pin<PORT, PIN, Output>, delay: DELAY) -> Self {
+        cs.set_high().void_unwrap();
+        SpiDevice { bus, cs, delay }
+    }
+
+    /// Return the bus, chip select pin and delay provider that make up this device
+    #[inline]
+    pub fn free(self) -> (SpiBus<USCI, PINS>, Pin<PORT, PIN, Output>, DELAY) {
+        (self.bus, self.cs, self.delay)
+    }
+}
+
+// Runs the operations of a single `SpiDevice` transaction. Chip select is handled by the caller.
+fn run_operations<USCI: SpiUsci, PINS, DELAY: DelayNs>(
+    bus: &mut SpiBus<USCI, PINS>,
+    delay: &mut DELAY,
+    operations: &mut [Operation<'_, u8>],
+) -> Result<(), SPIErr> {
+    use embedded_hal_1::spi::SpiBus as Bus;
+    for op in operations {
+        match op {
+            Operation::Read(words) => Bus::read(bus, words)?,
+            Operation::Write(words) => Bus::write(bus, words)?,
+            Operation::Transfer(read, write) => Bus::transfer(bus, read, write)?,
+            Operation::TransferInPlace(words) => Bus::transfer_in_place(bus, words)?,
+            Operation::DelayNs(ns) => {
+                Bus::flush(bus)?;
+                delay.delay_ns(*ns);
+            }
+        }
+    }
+    Bus::flush(bus)
+}
+
+impl<USCI: SpiUsci, PINS, PORT: PortNum, PIN: PinNum, DELAY> ErrorType
+    for SpiDevice<USCI, PINS, PORT, PIN, DELAY>
+{
+    type Error = SPIErr;
+}
+
+impl<USCI: SpiUsci, PINS, PORT: PortNum, PIN: PinNum, DELAY: DelayNs> embedded_hal_1::spi::SpiDevice<u8>
+    for SpiDevice<USCI, PINS, PORT, PIN, DELAY>
+{
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.cs.set_low().void_unwrap();
+        let res = run_operations(&mut self.bus, &mut self.delay, operations);
+        self.cs.set_high().void_unwrap();
+        res
+    }
+}
+
+/// Bus settings used by a single device on a `SharedSpiBus`
+#[derive(Clone, Copy, PartialEq)]
+pub struct SpiDeviceConfig {
+    /// SPI mode (clock polarity and phase)
+    pub mode: Mode,
+    /// Bit order of each transmitted byte
+    pub bit_order: BitOrder,
+    /// Target bus clock frequency in Hz. The bus runs as close to this as possible without exceeding it.
+    pub frequency: u32,
+}
+
+/// Shares an SPI bus between multiple devices, each with its own chip select pin and bus settings.
+///
+/// Devices are created with `device()` and borrow the shared bus. Before each transaction the
+/// eUSCI is reconfigured with the device's mode, bit order and frequency, but only if the
+/// previous transaction was made with different settings.
+pub struct SharedSpiBus<USCI: SpiUsci, PINS> {
+    bus: RefCell<SpiBus<USCI, PINS>>,
+    active: Cell<Option<SpiDeviceConfig>>,
+}
+
+impl<USCI: SpiUsci, PINS> SharedSpiBus<USCI, PINS> {
+    /// Take ownership of an SPI bus so it can be shared between devices.
+    /// The bus should be configured with `configure_with_software_cs()`.
+    #[inline]
+    pub fn new(bus: SpiBus<USCI, PINS>) -> Self {
+        SharedSpiBus {
+            bus: RefCell::new(bus),
+            active: Cell::new(None),
+        }
+    }
+
+    /// Create a device on this bus. The chip select pin is deasserted (driven high).
+    #[inline]
+    pub fn device<PORT: PortNum, PIN: PinNum, DELAY: DelayNs>(
+        &self,
+        mut cs: Pin<PORT, PIN, Output>,
+        config: SpiDeviceConfig,
+        delay: DELAY,
+    ) -> SharedSpiDevice<'_, USCI, PINS, PORT, PIN, DELAY> {
+        cs.set_high().void_unwrap();
+        SharedSpiDevice {
+            shared: self,
+            cs,
+            config,
+            delay,
+        }
+    }
+
+    /// Return the underlying SPI bus. All devices must have been dropped or freed first.
+    #[inline]
+    pub fn free(self) -> SpiBus<USCI, PINS> {
+        self.bus.into_inner()
+    }
+}
+
+/// A device on a `SharedSpiBus`, using a GPIO pin as chip select.
+///
+/// Implements the embedded-hal 1.0 `SpiDevice` trait.
+pub struct SharedSpiDevice<'a, USCI: SpiUsci, PINS, PORT: PortNum, PIN: PinNum, DELAY> {
+    shared: &'a SharedSpiBus<USCI, PINS>,
+    cs: Pin<PORT, PIN, Output>,
+    config: SpiDeviceConfig,
+    delay: DELAY,
+}
+
+impl<'a, USCI: SpiUsci, PINS, PORT: PortNum, PIN: PinNum, DELAY> SharedSpiDevice<'a, USCI, PINS, PORT, PIN, DELAY> {
+    /// The bus settings used by this device
+    #[inline]
+    pub fn config(&self) -> SpiDeviceConfig {
+        self.config
+    }
+
+    /// Change the bus settings used by this device. They are applied at the start of the next transaction.
+    #[inline]
+    pub fn set_config(&mut self, config: SpiDeviceConfig) {
+        self.config = config;
+    }
+
+    /// Return the chip select pin and delay provider used by this device
+    #[inline]
+    pub fn free(self) -> (Pin<PORT, PIN, Output>, DELAY) {
+        (self.cs, self.delay)
+    }
+}
+
+impl<'a, USCI: SpiUsci, PINS, PORT: PortNum, PIN: PinNum, DELAY> ErrorType
+    for SharedSpiDevice<'a, USCI, PINS, PORT, PIN, DELAY>
+{
+    type Error = SPIErr;
+}
+
+impl<'a, USCI: SpiUsci, PINS, PORT: PortNum, PIN: PinNum, DELAY: DelayNs>
+    embedded_hal_1::spi::SpiDevice<u8> for SharedSpiDevice<'a, USCI, PINS, PORT, PIN, DELAY>
+{
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let mut bus = self.shared.bus.try_borrow_mut().map_err(|_| SPIErr::BusBusy)?;
+        if self.shared.active.get() != Some(self.config) {
+            bus.apply_device_config(&self.config);
+            self.shared.active.set(Some(self.config));
+        }
+
+        self.cs.set_low().void_unwrap();
+        let res = run_operations(&mut bus, &mut self.delay, operations);
+        self.cs.set_high().void_unwrap();
+        res
+    }
+}
@@ -0,0 +1,289 @@
+//! SPI NOR flash driver
+//!
+//! Requires the `spi-nor` feature. Drives JEDEC-compatible serial NOR flash chips such as the
+//! Winbond W25Qxx family through any embedded-hal 1.0 `SpiDevice`, such as `spi::SpiDevice` or
+//! `spi::SharedSpiDevice`. The device must use SPI mode 0 or 3 with MSB first bit order.
+//!
+//! Only 3-byte addressing is supported, which covers chips of up to 16 MiB.
+
+use embedded_hal_1::spi::{Error, ErrorKind, Operation, SpiDevice};
+
+/// Size of a program page in bytes. A single page program can't cross a page boundary.
+pub const PAGE_SIZE: u32 = 256;
+
+const CMD_WRITE_ENABLE: u8 = 0x06;
+const CMD_READ_STATUS_1: u8 = 0x05;
+const CMD_READ_DATA: u8 = 0x03;
+const CMD_PAGE_PROGRAM: u8 = 0x02;
+const CMD_SECTOR_ERASE_4K: u8 = 0x20;
+const CMD_BLOCK_ERASE_32K: u8 = 0x52;
+const CMD_BLOCK_ERASE_64K: u8 = 0xD8;
+const CMD_CHIP_ERASE: u8 = 0xC7;
+const CMD_POWER_DOWN: u8 = 0xB9;
+const CMD_RELEASE_POWER_DOWN: u8 = 0xAB;
+const CMD_READ_JEDEC_ID: u8 = 0x9F;
+const CMD_READ_SFDP: u8 = 0x5A;
+
+const STATUS_WIP: u8 = 0x01;
+
+// "SFDP" in little endian
+const SFDP_SIGNATURE: u32 = 0x5044_4653;
+
+// Chip erase can take minutes on large parts, so this is deliberately generous. Its only purpose
+// is to stop a missing chip (MISO floating high, so WIP always reads as set) from hanging forever.
+const WIP_POLL_LIMIT: u32 = 50_000_000;
+
+/// JEDEC manufacturer and device ID
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JedecId {
+    /// Manufacturer ID (e.g. 0xEF for Winbond)
+    pub manufacturer: u8,
+    /// Memory type
+    pub memory_type: u8,
+    /// Capacity code, usually log2 of the size in bytes
+    pub capacity: u8,
+}
+
+/// Information read from the chip's SFDP (Serial Flash Discoverable Parameters) table
+#[derive(Clone, Copy, Debug)]
+pub struct SfdpInfo {
+    /// SFDP major revision
+    pub major_rev: u8,
+    /// SFDP minor revision
+    pub minor_rev: u8,
+    /// Flash size in bytes
+    pub capacity: u32,
+}
+
+/// Erase granularity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EraseSize {
+    /// 4 KiB sector
+    Sector4K,
+    /// 32 KiB block
+    Block32K,
+    /// 64 KiB block
+    Block64K,
+}
+
+impl EraseSize {
+    /// Size in bytes
+    #[inline]
+    pub fn bytes(self) -> u32 {
+        match self {
+            EraseSize::Sector4K => 4 * 1024,
+            EraseSize::Block32K => 32 * 1024,
+            EraseSize::Block64K => 64 * 1024,
+        }
+    }
+
+    #[inline(always)]
+    fn command(self) -> u8 {
+        match self {
+            EraseSize::Sector4K => CMD_SECTOR_ERASE_4K,
+            EraseSize::Block32K => CMD_BLOCK_ERASE_32K,
+            EraseSize::Block64K => CMD_BLOCK_ERASE_64K,
+        }
+    }
+}
+
+/// SPI NOR flash errors
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum NorFlashError {
+    /// The underlying SPI device reported an error
+    Spi(ErrorKind),
+    /// The chip stayed busy for too long
+    Timeout,
+    /// The erase address isn't aligned to the erase size
+    NotAligned,
+    /// The accessed range doesn't fit in 3-byte addresses
+    OutOfBounds,
+    /// The chip doesn't have a valid SFDP table
+    InvalidSfdp,
+}
+
+/// A SPI NOR flash chip connected to an SPI device
+pub struct SpiNorFlash<SPI> {
+    spi: SPI,
+}
+
+impl<SPI: SpiDevice> SpiNorFlash<SPI> {
+    /// Create a new flash driver
+    #[inline]
+    pub fn new(spi: SPI) -> Self {
+        SpiNorFlash { spi }
+    }
+
+    /// Return the SPI device
+    #[inline]
+    pub fn free(self) -> SPI {
+        self.spi
+    }
+
+    /// Read the JEDEC manufacturer and device ID
+    pub fn read_jedec_id(&mut self) -> Result<JedecId, NorFlashError> {
+        let mut id = [0; 3];
+        self.transaction(&mut [
+            Operation::Write(&[CMD_READ_JEDEC_ID]),
+            Operation::Read(&mut id),
+        ])?;
+        Ok(JedecId {
+            manufacturer: id[0],
+            memory_type: id[1],
+            capacity: id[2],
+        })
+    }
+
+    /// Read raw bytes from the SFDP address space
+    pub fn read_sfdp(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), NorFlashError> {
+        let [_, a2, a1, a0] = check_range(addr, buf.len())?.to_be_bytes();
+        // The read is followed by 8 dummy clocks
+        self.transaction(&mut [
+            Operation::Write(&[CMD_READ_SFDP, a2, a1, a0, 0xFF]),
+            Operation::Read(buf),
+        ])
+    }
+
+    /// Read the SFDP header and the flash density from the basic flash parameter table
+    pub fn read_sfdp_info(&mut self) -> Result<SfdpInfo, NorFlashError> {
+        let mut header = [0; 16];
+        self.read_sfdp(0, &mut header)?;
+        if u32::from_le_bytes([header[0], header[1], header[2], header[3]]) != SFDP_SIGNATURE {
+            return Err(NorFlashError::InvalidSfdp);
+        }
+
+        // The first parameter header always points to the basic flash parameter table, whose
+        // second DWORD holds the density
+        let table_ptr = u32::from_le_bytes([header[12], header[13], header[14], 0]);
+        let mut density = [0; 4];
+        self.read_sfdp(table_ptr + 4, &mut density)?;
+        let density = u32::from_le_bytes(density);
+
+        // Bit 31 selects between a bit count of `density + 1` and one of 2^density
+        let capacity = if density & 0x8000_0000 == 0 {
+            (density + 1) / 8
+        } else {
+            let exp = density & 0x7FFF_FFFF;
+            if !(3..35).contains(&exp) {
+                return Err(NorFlashError::InvalidSfdp);
+            }
+            1 << (exp - 3)
+        };
+
+        Ok(SfdpInfo {
+            minor_rev: header[4],
+            major_rev: header[5],
+            capacity,
+        })
+    }
+
+    /// Read status register 1
+    pub fn read_status(&mut self) -> Result<u8, NorFlashError> {
+        let mut status = [0];
+        self.transaction(&mut [
+            Operation::Write(&[CMD_READ_STATUS_1]),
+            Operation::Read(&mut status),
+        ])?;
+        Ok(status[0])
+    }
+
+    /// Whether a program or erase operation is in progress
+    #[inline]
+    pub fn is_busy(&mut self) -> Result<bool, NorFlashError> {
+        Ok(self.read_status()? & STATUS_WIP != 0)
+    }
+
+    /// Read `buf.len()` bytes starting at `addr`
+    pub fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), NorFlashError> {
+        let [_, a2, a1, a0] = check_range(addr, buf.len())?.to_be_bytes();
+        self.transaction(&mut [
+            Operation::Write(&[CMD_READ_DATA, a2, a1, a0]),
+            Operation::Read(buf),
+        ])
+    }
+
+    /// Program `data` starting at `addr`. The write is split into page programs so that none of
+    /// them cross a page boundary. The target area should have been erased first.
+    pub fn write(&mut self, mut addr: u32, mut data: &[u8]) -> Result<(), NorFlashError> {
+        check_range(addr, data.len())?;
+        while !data.is_empty() {
+            let page_remaining = (PAGE_SIZE - addr % PAGE_SIZE) as usize;
+            let (chunk, rest) = data.split_at(page_remaining.min(data.len()));
+            let [_, a2, a1, a0] = addr.to_be_bytes();
+
+            self.write_enable()?;
+            self.transaction(&mut [
+                Operation::Write(&[CMD_PAGE_PROGRAM, a2, a1, a0]),
+                Operation::Write(chunk),
+            ])?;
+            self.wait_idle()?;
+
+            addr += chunk.len() as u32;
+            data = rest;
+        }
+        Ok(())
+    }
+
+    /// Erase the sector or block containing `addr`, which must be aligned to the erase size.
+    /// Erased bytes read back as 0xFF.
+    pub fn erase(&mut self, addr: u32, size: EraseSize) -> Result<(), NorFlashError> {
+        if !addr.is_multiple_of(size.bytes()) {
+            return Err(NorFlashError::NotAligned);
+        }
+        let [_, a2, a1, a0] = check_range(addr, size.bytes() as usize)?.to_be_bytes();
+
+        self.write_enable()?;
+        self.transaction(&mut [Operation::Write(&[size.command(), a2, a1, a0])])?;
+        self.wait_idle()
+    }
+
+    /// Erase the whole chip. This can take a long time on large chips.
+    pub fn erase_chip(&mut self) -> Result<(), NorFlashError> {
+        self.write_enable()?;
+        self.transaction(&mut [Operation::Write(&[CMD_CHIP_ERASE])])?;
+        self.wait_idle()
+    }
+
+    /// Put the chip into its low power mode. It ignores all commands except `wake_up()` until woken.
+    pub fn power_down(&mut self) -> Result<(), NorFlashError> {
+        self.transaction(&mut [Operation::Write(&[CMD_POWER_DOWN])])
+    }
+
+    /// Wake the chip from low power mode. The chip needs a short time (tRES1, typically 3 us)
+    /// before it accepts other commands.
+    pub fn wake_up(&mut self) -> Result<(), NorFlashError> {
+        self.transaction(&mut [Operation::Write(&[CMD_RELEASE_POWER_DOWN])])
+    }
+
+    #[inline]
+    fn write_enable(&mut self) -> Result<(), NorFlashError> {
+        self.transaction(&mut [Operation::Write(&[CMD_WRITE_ENABLE])])
+    }
+
+    fn wait_idle(&mut self) -> Result<(), NorFlashError> {
+        for _ in 0..WIP_POLL_LIMIT {
+            if !self.is_busy()? {
+                return Ok(());
+            }
+        }
+        Err(NorFlashError::Timeout)
+    }
+
+    // Run a single command, with CS asserted for its duration
+    #[inline]
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), NorFlashError> {
+        self.spi
+            .transaction(operations)
+            .map_err(|err| NorFlashError::Spi(err.kind()))
+    }
+}
+
+#[inline(always)]
+fn check_range(addr: u32, len: usize) -> Result<u32, NorFlashError> {
+    if addr as u64 + len as u64 > 0x0100_0000 {
+        Err(NorFlashError::OutOfBounds)
+    } else {
+        Ok(addr)
+    }
+}
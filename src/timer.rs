@@ -350,3 +350,18 @@ impl<T: CapCmp<C>, C> SubTimer<T, C> {
         timer.ccie_clr();
     }
 }
+
+impl<T: TimerPeriph + CapCmp<C>, C> SubTimer<T, C> {
+    /// Set the sub-timer to fire `ticks` timer counts from now, wrapping around at the main
+    /// timer's threshold. Requires the main timer to be running, and `ticks` must be less than
+    /// the main threshold.
+    #[inline]
+    pub fn set_timeout(&mut self, ticks: u16) {
+        let timer = unsafe { T::steal() };
+        // The main timer counts from 0 up to and including its threshold
+        let period = CCRn::<CCR0>::get_ccrn(&timer) as u32 + 1;
+        let count = (timer.tbr_rd() as u32 + ticks as u32) % period;
+        CCRn::<C>::set_ccrn(&timer, count as u16);
+        CCRn::<C>::ccifg_clr(&timer);
+    }
+}